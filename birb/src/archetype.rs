@@ -0,0 +1,239 @@
+use list_any::VecAny;
+use parking_lot::RwLock;
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{component::ComponentInfo, entity::Entity};
+
+pub struct Column {
+    pub info: ComponentInfo,
+    pub data: RwLock<VecAny>,
+    /// The change tick at which each row was last mutably accessed. These are atomics rather
+    /// than part of `data` so that filtering on them doesn't need the column's lock.
+    pub changed: Vec<AtomicU64>,
+}
+
+impl Column {
+    /// Records that `row` was mutably accessed at `tick`
+    pub fn mark(&self, row: usize, tick: u64) {
+        self.changed[row].store(tick, Ordering::Relaxed);
+    }
+
+    /// Records that every row was mutably accessed at `tick`
+    pub fn mark_all(&self, tick: u64) {
+        for changed in &self.changed {
+            changed.store(tick, Ordering::Relaxed);
+        }
+    }
+
+    pub fn changed_at(&self, row: usize) -> u64 {
+        self.changed[row].load(Ordering::Relaxed)
+    }
+}
+
+/// Every entity with exactly the same set of components, stored as one column per component so
+/// that iterating a single component stays contiguous
+pub struct Archetype {
+    /// Sorted by component id
+    columns: Vec<Column>,
+    /// The entity in each row
+    pub entities: Vec<Entity>,
+}
+
+impl Archetype {
+    /// `infos` must be sorted and free of duplicates
+    fn new(infos: &[ComponentInfo]) -> Self {
+        Self {
+            columns: infos
+                .iter()
+                .map(|info| Column {
+                    info: *info,
+                    data: RwLock::new((info.new_column)()),
+                    changed: Vec::new(),
+                })
+                .collect(),
+            entities: Vec::new(),
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn infos(&self) -> impl Iterator<Item = ComponentInfo> + '_ {
+        self.columns.iter().map(|column| column.info)
+    }
+
+    fn index(&self, id: TypeId) -> Option<usize> {
+        self.columns
+            .binary_search_by_key(&id, |column| column.info.id())
+            .ok()
+    }
+
+    pub fn contains(&self, id: TypeId) -> bool {
+        self.index(id).is_some()
+    }
+
+    pub fn column(&self, id: TypeId) -> Option<&Column> {
+        self.index(id).map(|index| &self.columns[index])
+    }
+
+    pub fn column_mut(&mut self, id: TypeId) -> Option<&mut Column> {
+        self.index(id).map(|index| &mut self.columns[index])
+    }
+
+    /// Drops every component in `row` by moving the last row into its place, returning the
+    /// entity which was moved
+    pub fn swap_remove(&mut self, row: usize) -> Option<Entity> {
+        for column in &mut self.columns {
+            (column.info.swap_remove)(column.data.get_mut(), row);
+            column.changed.swap_remove(row);
+        }
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+
+    /// Moves the components in `row` onto the end of `to`, returning the entity which was moved
+    /// into `row`.
+    ///
+    /// Components which `to` lacks, or which are `replaced`, are passed to `extract` which must
+    /// `swap_remove` the row from the column. The caller is responsible for pushing the entity
+    /// and any missing components onto `to`. Change ticks move along with their components.
+    pub fn move_row(
+        &mut self,
+        row: usize,
+        to: &mut Self,
+        replaced: &[TypeId],
+        mut extract: impl FnMut(&ComponentInfo, &mut VecAny),
+    ) -> Option<Entity> {
+        for column in &mut self.columns {
+            let id = column.info.id();
+            let changed = column.changed.swap_remove(row);
+            match to.column_mut(id) {
+                Some(target) if !replaced.contains(&id) => {
+                    (column.info.move_row)(column.data.get_mut(), row, target.data.get_mut());
+                    target.changed.push(changed);
+                }
+                _ => extract(&column.info, column.data.get_mut()),
+            }
+        }
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+}
+
+#[derive(Default)]
+pub struct Archetypes {
+    all: Vec<Archetype>,
+    by_components: HashMap<Vec<TypeId>, usize>,
+    /// The archetypes containing each component
+    by_component: HashMap<TypeId, Vec<usize>>,
+}
+
+impl Archetypes {
+    /// Finds the archetype with exactly the components in `infos`, creating it if necessary
+    ///
+    /// # Panics
+    /// Panics if `infos` contains the same component twice
+    pub fn get_or_insert(&mut self, mut infos: Vec<ComponentInfo>) -> usize {
+        infos.sort_unstable_by_key(ComponentInfo::id);
+        if let Some(pair) = infos.windows(2).find(|pair| pair[0].id() == pair[1].id()) {
+            panic!("{} appears more than once", pair[0].name());
+        }
+
+        let ids: Vec<TypeId> = infos.iter().map(ComponentInfo::id).collect();
+        if let Some(index) = self.by_components.get(&ids) {
+            return *index;
+        }
+
+        let index = self.all.len();
+        self.all.push(Archetype::new(&infos));
+        for id in &ids {
+            self.by_component.entry(*id).or_default().push(index);
+        }
+        self.by_components.insert(ids, index);
+        index
+    }
+
+    pub fn get(&self, index: usize) -> &Archetype {
+        &self.all[index]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut Archetype {
+        &mut self.all[index]
+    }
+
+    /// # Panics
+    /// Panics if `a` and `b` are the same archetype
+    pub fn pair_mut(&mut self, a: usize, b: usize) -> (&mut Archetype, &mut Archetype) {
+        assert_ne!(a, b);
+        if a < b {
+            let (left, right) = self.all.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        } else {
+            let (left, right) = self.all.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Archetype> {
+        self.all.iter()
+    }
+
+    /// Every archetype containing the component `id`, or `None` if no entity has ever had it
+    pub fn containing(&self, id: TypeId) -> Option<impl Iterator<Item = &Archetype>> {
+        self.by_component
+            .get(&id)
+            .map(|indices| indices.iter().map(|index| &self.all[*index]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{App, Error};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Position(f32);
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Velocity(f32);
+    #[derive(Debug, PartialEq)]
+    struct Frozen;
+
+    #[test]
+    fn test_structural_changes() {
+        let mut app = App::new();
+        let a = app.spawn((Position(0.0), Velocity(1.0)));
+        let b = app.spawn((Position(1.0),));
+        let c = app.spawn((Velocity(2.0), Position(2.0)));
+
+        app.insert(a, (Frozen, Position(5.0))).unwrap();
+        assert_eq!(*app.get::<Position>(a).unwrap(), Position(5.0));
+        assert_eq!(*app.get::<Velocity>(a).unwrap(), Velocity(1.0));
+        assert_eq!(*app.get::<Velocity>(c).unwrap(), Velocity(2.0));
+        assert!(app.contains::<Frozen>(a));
+
+        assert_eq!(app.remove::<Velocity>(a).unwrap(), Velocity(1.0));
+        assert!(matches!(
+            app.remove::<Velocity>(a),
+            Err(Error::MissingComponent(_))
+        ));
+        assert!(app.get::<Velocity>(a).is_err());
+        assert_eq!(*app.get::<Position>(a).unwrap(), Position(5.0));
+
+        let mut positions: Vec<f32> = app
+            .get_entity::<Position>()
+            .unwrap()
+            .iter()
+            .map(|position| position.0)
+            .collect();
+        positions.sort_by(f32::total_cmp);
+        assert_eq!(positions, [1.0, 2.0, 5.0]);
+
+        app.despawn(b).unwrap();
+        assert_eq!(app.get_entity::<Position>().unwrap().len(), 2);
+        assert!(app.get_entity::<u8>().is_err());
+    }
+}
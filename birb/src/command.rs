@@ -0,0 +1,243 @@
+use std::{any::type_name, fmt};
+
+use crate::{
+    component::{Bundle, Component},
+    entity::Entity,
+    state::States,
+    App, Error, Module, ModuleId,
+};
+
+pub(crate) type Command = Box<dyn FnOnce(&mut App) + Send>;
+
+/// Records structural changes through `&App`. They are applied in order once the current tick
+/// has finished running every module, or by [`App::apply_commands`].
+#[derive(Clone, Copy)]
+pub struct Commands<'a> {
+    pub(crate) app: &'a App,
+}
+
+impl Commands<'_> {
+    /// Queues an arbitrary change to the app
+    pub fn add(&self, command: impl FnOnce(&mut App) + Send + 'static) {
+        self.app.commands.lock().push(Box::new(command));
+    }
+
+    /// Queues spawning an entity made up of the components in `bundle`. The returned handle
+    /// can be used straight away, for example in further commands, but the entity has no
+    /// components until the commands are applied.
+    pub fn spawn<B: Bundle>(&self, bundle: B) -> Entity {
+        let entity = self.app.entities.reserve();
+        self.add(move |app| app.spawn_reserved(entity, bundle));
+        entity
+    }
+
+    /// Queues [`App::despawn`]
+    pub fn despawn(&self, entity: Entity) {
+        self.add(move |app| {
+            let _ = app.despawn(entity);
+        });
+    }
+
+    /// Queues [`App::set_parent`]
+    pub fn set_parent(&self, child: Entity, parent: Entity) {
+        self.add(move |app| {
+            let _ = app.set_parent(child, parent);
+        });
+    }
+
+    /// Queues [`App::remove_parent`]
+    pub fn remove_parent(&self, child: Entity) {
+        self.add(move |app| {
+            let _ = app.remove_parent(child);
+        });
+    }
+
+    /// Queues [`App::insert`]
+    pub fn insert<B: Bundle>(&self, entity: Entity, bundle: B) {
+        self.add(move |app| {
+            let _ = app.insert(entity, bundle);
+        });
+    }
+
+    /// Queues [`App::remove`], dropping the removed component
+    pub fn remove<T: Component>(&self, entity: Entity) {
+        self.add(move |app| {
+            let _ = app.remove::<T>(entity);
+        });
+    }
+
+    /// Queues [`App::register_module`]
+    ///
+    /// # Panics
+    /// As there is nobody to return an error to, applying the command panics if the module
+    /// cannot be registered
+    pub fn register_module<T: 'static + Module>(&self, module: T) {
+        self.add(move |app| {
+            if let Err(err) = app.register_module(module) {
+                panic!("failed to register {}: {err}", type_name::<T>());
+            }
+        });
+    }
+
+    /// Queues [`App::register_module_as`]
+    ///
+    /// # Panics
+    /// As there is nobody to return an error to, applying the command panics if the module
+    /// cannot be registered
+    pub fn register_module_as<T: 'static + Module>(&self, label: &'static str, module: T) {
+        self.add(move |app| {
+            if let Err(err) = app.register_module_as(label, module) {
+                panic!("failed to register {}: {err}", type_name::<T>());
+            }
+        });
+    }
+
+    /// Queues [`App::remove_module`], dropping the removed module
+    pub fn remove_module<T: 'static + Module>(&self) {
+        self.add(|app| {
+            let _ = app.remove_module::<T>();
+        });
+    }
+
+    /// Queues [`App::set_enabled`], doing nothing if the module is not registered
+    pub fn set_enabled(&self, module: ModuleId, enabled: bool) {
+        self.add(move |app| {
+            let _ = app.set_enabled(module, enabled);
+        });
+    }
+
+    /// Queues [`App::set_state`], doing nothing if no [`State<S>`](crate::state::State) is
+    /// registered
+    pub fn set_state<S: States>(&self, next: S) {
+        self.add(move |app| {
+            let _ = app.set_state(next);
+        });
+    }
+
+    /// Queues [`App::replace_module`]
+    ///
+    /// # Panics
+    /// As there is nobody to return an error to, applying the command panics if the new
+    /// module cannot be registered. Nothing happens if no `Old` is registered.
+    pub fn replace_module<Old: 'static + Module, New: 'static + Module>(
+        &self,
+        replace: impl FnOnce(&mut Old) -> New + Send + 'static,
+    ) {
+        self.add(move |app| match app.replace_module(replace) {
+            Ok(()) | Err(Error::MissingModule(_)) => {}
+            Err(err) => panic!("failed to register {}: {err}", type_name::<New>()),
+        });
+    }
+
+    /// Queues [`App::replace_module_as`]
+    ///
+    /// # Panics
+    /// As there is nobody to return an error to, applying the command panics if the new
+    /// module cannot be registered. Nothing happens if no `Old` is registered under `label`.
+    pub fn replace_module_as<Old: 'static + Module, New: 'static + Module>(
+        &self,
+        label: &'static str,
+        replace: impl FnOnce(&mut Old) -> New + Send + 'static,
+    ) {
+        self.add(move |app| match app.replace_module_as(label, replace) {
+            Ok(()) | Err(Error::MissingModule(_)) => {}
+            Err(err) => panic!("failed to register {}: {err}", type_name::<New>()),
+        });
+    }
+}
+
+impl fmt::Debug for Commands<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Commands")
+            .field("queued", &self.app.commands.lock().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{schedule::ScheduleError, App, Error, Module};
+
+    #[derive(Debug)]
+    struct Spawner;
+
+    impl Module for Spawner {
+        fn tick(&mut self, app: &App) {
+            let bullet = app.commands().spawn((0u32,));
+            app.commands().insert(bullet, (1u8,));
+            app.commands().register_module(Spawned);
+        }
+    }
+
+    #[derive(Debug)]
+    struct Spawned;
+
+    impl Module for Spawned {}
+
+    #[test]
+    fn test_commands() {
+        let mut app = App::new();
+        app.register_module(Spawner).unwrap();
+
+        app.tick();
+        assert_eq!(app.get_entity::<u32>().unwrap().len(), 1);
+        assert_eq!(app.get_entity::<u8>().unwrap().len(), 1);
+        assert!(app.get_module::<Spawned>().is_ok());
+
+        let reserved = app.commands().spawn((2u32,));
+        app.spawn((3u32,));
+        app.commands().despawn(reserved);
+        app.apply_commands();
+        assert!(app.get::<u32>(reserved).is_err());
+        assert_eq!(app.get_entity::<u32>().unwrap().len(), 2);
+    }
+
+    #[derive(Debug)]
+    struct EditorCamera(u32);
+
+    impl Module for EditorCamera {
+        fn tick(&mut self, app: &App) {
+            self.0 += 1;
+            app.commands()
+                .replace_module(|editor: &mut Self| GameplayCamera(editor.0));
+        }
+    }
+
+    #[derive(Debug)]
+    struct GameplayCamera(u32);
+
+    impl Module for GameplayCamera {}
+
+    #[test]
+    fn test_replace_module() {
+        let mut app = App::new();
+        app.register_module(EditorCamera(1)).unwrap();
+        app.tick();
+        assert!(app.get_module::<EditorCamera>().is_err());
+        assert_eq!(app.get_module::<GameplayCamera>().unwrap().0, 2);
+
+        app.commands().remove_module::<GameplayCamera>();
+        app.tick();
+        assert!(app.get_module::<GameplayCamera>().is_err());
+        assert!(app.remove_module::<GameplayCamera>().is_err());
+        assert!(!app.execution_plan().to_string().contains("Camera"));
+
+        app.register_module_as("minimap", EditorCamera(5)).unwrap();
+        app.commands()
+            .replace_module_as("minimap", |editor: &mut EditorCamera| {
+                GameplayCamera(editor.0)
+            });
+        app.apply_commands();
+        assert_eq!(app.get_module_as::<GameplayCamera>("minimap").unwrap().0, 5);
+
+        // The old module stays put if the new one is rejected
+        app.register_module(EditorCamera(7)).unwrap();
+        app.register_module(GameplayCamera(0)).unwrap();
+        assert!(matches!(
+            app.replace_module(|editor: &mut EditorCamera| GameplayCamera(editor.0)),
+            Err(Error::Schedule(ScheduleError::Duplicate(_)))
+        ));
+        assert_eq!(app.get_module::<EditorCamera>().unwrap().0, 7);
+        assert!(app.execution_plan().to_string().contains("EditorCamera"));
+    }
+}
@@ -0,0 +1,268 @@
+use list_any::VecAny;
+use rayon::prelude::*;
+use std::{
+    any::{type_name, TypeId},
+    fmt::{self, Debug},
+    sync::atomic::AtomicU64,
+};
+
+use crate::{
+    archetype::Archetype,
+    lock::{ReadGuard, WriteGuard},
+};
+
+/// The change ticks of the code currently running. Every module run gets a tick of its own,
+/// and anything mutably accessed since `last_run` counts as changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticks {
+    /// The tick of the previous run of the module, or 0 if it has not run before or if no
+    /// module is running
+    pub(crate) last_run: u64,
+    /// The tick to mark anything mutably accessed with
+    pub(crate) this_run: u64,
+}
+
+impl Ticks {
+    pub(crate) const fn is_changed(self, tick: u64) -> bool {
+        tick > self.last_run
+    }
+}
+
+/// Any type which can be attached to an entity
+pub trait Component: 'static + Send + Sync {}
+
+impl<T: 'static + Send + Sync> Component for T {}
+
+/// Type-erased operations on a column of one component type
+#[derive(Clone, Copy)]
+pub struct ComponentInfo {
+    id: TypeId,
+    name: &'static str,
+    pub(crate) new_column: fn() -> VecAny,
+    pub(crate) swap_remove: fn(&mut VecAny, usize),
+    pub(crate) move_row: fn(&mut VecAny, usize, &mut VecAny),
+}
+
+impl ComponentInfo {
+    #[must_use]
+    pub fn of<T: Component>() -> Self {
+        Self {
+            id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+            new_column: || VecAny::new::<T>(),
+            swap_remove: swap_remove::<T>,
+            move_row: move_row::<T>,
+        }
+    }
+
+    #[must_use]
+    pub const fn id(&self) -> TypeId {
+        self.id
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+fn swap_remove<T: Component>(column: &mut VecAny, row: usize) {
+    column.downcast_mut::<T>().unwrap().swap_remove(row);
+}
+
+fn move_row<T: Component>(from: &mut VecAny, row: usize, to: &mut VecAny) {
+    let component = from.downcast_mut::<T>().unwrap().swap_remove(row);
+    to.downcast_mut::<T>().unwrap().push(component);
+}
+
+impl Debug for ComponentInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ComponentInfo").field(&self.name).finish()
+    }
+}
+
+/// A set of components which are added to an entity together.
+///
+/// Implemented for tuples of up to twelve components.
+pub trait Bundle: 'static + Send + Sync {
+    /// Every component in the bundle, each type may only appear once
+    fn components() -> Vec<ComponentInfo>;
+
+    /// Writes every component listed by [`Bundle::components`]
+    fn write(self, writer: &mut BundleWriter<'_>);
+}
+
+/// Stores the components of a bundle into an entity
+pub struct BundleWriter<'a> {
+    archetype: &'a mut Archetype,
+    row: Option<usize>,
+    tick: u64,
+}
+
+impl<'a> BundleWriter<'a> {
+    /// Appends the bundle as a new row, marking its components as changed at `tick`
+    pub(crate) const fn push(archetype: &'a mut Archetype, tick: u64) -> Self {
+        Self {
+            archetype,
+            row: None,
+            tick,
+        }
+    }
+
+    /// Overwrites the components of an existing row, marking them as changed at `tick`
+    pub(crate) const fn replace(archetype: &'a mut Archetype, row: usize, tick: u64) -> Self {
+        Self {
+            archetype,
+            row: Some(row),
+            tick,
+        }
+    }
+
+    /// # Panics
+    /// Panics if `T` was not listed by [`Bundle::components`]
+    pub fn write<T: Component>(&mut self, component: T) {
+        let column = self
+            .archetype
+            .column_mut(TypeId::of::<T>())
+            .unwrap_or_else(|| panic!("{} is not part of the bundle", type_name::<T>()));
+        let mut values = column.data.get_mut().downcast_mut::<T>().unwrap();
+        if let Some(row) = self.row {
+            values[row] = component;
+            column.changed[row] = AtomicU64::new(self.tick);
+        } else {
+            values.push(component);
+            column.changed.push(AtomicU64::new(self.tick));
+        }
+    }
+}
+
+/// A component whose type is only known at runtime, such as one read from a data file, which
+/// [`App::spawn_batch_with`](crate::App::spawn_batch_with) clones into every entity it spawns
+pub struct DynamicComponent {
+    info: ComponentInfo,
+    write: Box<dyn Fn(&mut BundleWriter<'_>) + Send + Sync>,
+}
+
+impl DynamicComponent {
+    pub fn new<T: Component + Clone>(component: T) -> Self {
+        Self {
+            info: ComponentInfo::of::<T>(),
+            write: Box::new(move |writer| writer.write(component.clone())),
+        }
+    }
+
+    #[must_use]
+    pub const fn info(&self) -> ComponentInfo {
+        self.info
+    }
+
+    pub(crate) fn write(&self, writer: &mut BundleWriter<'_>) {
+        (self.write)(writer);
+    }
+}
+
+impl Debug for DynamicComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DynamicComponent")
+            .field(&self.info.name)
+            .finish()
+    }
+}
+
+macro_rules! tuple_bundle {
+    ($($name:ident),*) => {
+        impl<$($name: Component),*> Bundle for ($($name,)*) {
+            fn components() -> Vec<ComponentInfo> {
+                vec![$(ComponentInfo::of::<$name>()),*]
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn write(self, writer: &mut BundleWriter<'_>) {
+                let ($($name,)*) = self;
+                $(writer.write($name);)*
+            }
+        }
+    };
+}
+
+tuple_bundle!();
+tuple_bundle!(A);
+tuple_bundle!(A, B);
+tuple_bundle!(A, B, C);
+tuple_bundle!(A, B, C, D);
+tuple_bundle!(A, B, C, D, E);
+tuple_bundle!(A, B, C, D, E, F);
+tuple_bundle!(A, B, C, D, E, F, G);
+tuple_bundle!(A, B, C, D, E, F, G, H);
+tuple_bundle!(A, B, C, D, E, F, G, H, I);
+tuple_bundle!(A, B, C, D, E, F, G, H, I, J);
+tuple_bundle!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_bundle!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Shared access to every component of one type, spread over the archetypes containing it
+pub struct Components<'a, T> {
+    pub(crate) columns: Vec<ReadGuard<'a, [T]>>,
+}
+
+impl<'a, T: Component> Components<'a, T> {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.columns.iter().map(|column| column.len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.columns.iter().all(|column| column.is_empty())
+    }
+
+    /// Each archetype's components as a contiguous slice
+    pub fn slices(&self) -> impl Iterator<Item = &[T]> + use<'_, 'a, T> {
+        self.columns.iter().map(|column| &**column)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + use<'_, 'a, T> {
+        self.slices().flatten()
+    }
+
+    #[must_use]
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = &T> + use<'_, 'a, T> {
+        self.columns.par_iter().flat_map(|column| column.par_iter())
+    }
+}
+
+/// Exclusive access to every component of one type, spread over the archetypes containing it
+pub struct ComponentsMut<'a, T> {
+    pub(crate) columns: Vec<WriteGuard<'a, [T]>>,
+}
+
+impl<'a, T: Component> ComponentsMut<'a, T> {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.columns.iter().map(|column| column.len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.columns.iter().all(|column| column.is_empty())
+    }
+
+    /// Each archetype's components as a contiguous slice
+    pub fn slices_mut(&mut self) -> impl Iterator<Item = &mut [T]> + use<'_, 'a, T> {
+        self.columns.iter_mut().map(|column| &mut **column)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + use<'_, 'a, T> {
+        self.columns.iter().flat_map(|column| column.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> + use<'_, 'a, T> {
+        self.slices_mut().flatten()
+    }
+
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut T> + use<'_, 'a, T> {
+        self.slices_mut()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .flat_map(<[T]>::par_iter_mut)
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A handle to a spawned entity.
+///
+/// Indices are reused once an entity is despawned, the generation is bumped each time so that
+/// handles to the old entity can be told apart from handles to the new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Rebuilds a handle from its index and generation, such as one saved to disk. The handle
+    /// only refers to the same entity within the app it came from.
+    #[must_use]
+    pub const fn from_raw(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    #[must_use]
+    pub const fn index(self) -> u32 {
+        self.index
+    }
+
+    #[must_use]
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+/// Where an entity's components are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Location {
+    pub archetype: usize,
+    pub row: usize,
+}
+
+#[derive(Debug)]
+struct Meta {
+    generation: u32,
+    location: Option<Location>,
+}
+
+/// Allocates entity handles and tracks where each live entity is stored
+#[derive(Debug, Default)]
+pub(crate) struct Entities {
+    meta: Vec<Meta>,
+    free: Vec<u32>,
+    /// Handles given out by [`Entities::reserve`] past the end of `meta`
+    reserved: AtomicU32,
+}
+
+impl Entities {
+    /// Hands out a fresh handle without needing exclusive access. The entity does not exist
+    /// until it is given a location with [`Entities::place`].
+    ///
+    /// Reserved handles never reuse the index of a despawned entity.
+    pub fn reserve(&self) -> Entity {
+        let offset = self.reserved.fetch_add(1, Ordering::Relaxed);
+        let index = u32::try_from(self.meta.len())
+            .ok()
+            .and_then(|len| len.checked_add(offset))
+            .expect("too many entities");
+        Entity {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Makes room in `meta` for every reserved handle
+    fn flush(&mut self) {
+        let reserved = std::mem::take(self.reserved.get_mut());
+        self.meta.extend((0..reserved).map(|_| Meta {
+            generation: 0,
+            location: None,
+        }));
+    }
+
+    /// Stores a handle returned by [`Entities::reserve`]
+    pub fn place(&mut self, entity: Entity, location: Location) {
+        self.flush();
+        self.meta[entity.index as usize].location = Some(location);
+    }
+
+    /// Makes `entity`'s index usable for exactly that handle, so it can be stored with
+    /// [`Entities::place`]. Returns `false` if another entity with the index is alive.
+    pub fn claim(&mut self, entity: Entity) -> bool {
+        self.flush();
+        let index = entity.index as usize;
+        if index >= self.meta.len() {
+            // Indices skipped over are free, claimed ones are left in `free` and skipped by
+            // `alloc` while alive
+            let start = u32::try_from(self.meta.len()).expect("too many entities");
+            self.free.extend(start..entity.index);
+            self.meta.extend((start..=entity.index).map(|_| Meta {
+                generation: 0,
+                location: None,
+            }));
+        }
+        let meta = &mut self.meta[index];
+        if meta.location.is_some() {
+            return false;
+        }
+        meta.generation = entity.generation;
+        true
+    }
+
+    pub fn alloc(&mut self, location: Location) -> Entity {
+        self.flush();
+        while let Some(index) = self.free.pop() {
+            let meta = &mut self.meta[index as usize];
+            if meta.location.is_some() {
+                continue;
+            }
+            meta.location = Some(location);
+            return Entity {
+                index,
+                generation: meta.generation,
+            };
+        }
+        let index = u32::try_from(self.meta.len()).expect("too many entities");
+        self.meta.push(Meta {
+            generation: 0,
+            location: Some(location),
+        });
+        Entity {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Releases `entity`, returning where it was stored, or `None` if the handle is stale
+    pub fn free(&mut self, entity: Entity) -> Option<Location> {
+        let meta = self.meta.get_mut(entity.index as usize)?;
+        if meta.generation != entity.generation {
+            return None;
+        }
+        let location = meta.location.take()?;
+        meta.generation = meta.generation.wrapping_add(1);
+        self.free.push(entity.index);
+        Some(location)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<Location> {
+        let meta = self.meta.get(entity.index as usize)?;
+        if meta.generation == entity.generation {
+            meta.location
+        } else {
+            None
+        }
+    }
+
+    /// Updates the location of a live entity after it has been moved
+    pub fn relocate(&mut self, entity: Entity, location: Location) {
+        self.meta[entity.index as usize].location = Some(location);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Entity;
+    use crate::{App, Error};
+
+    #[test]
+    fn test_despawn() {
+        let mut app = App::new();
+        let a = app.spawn((1u32,));
+        let b = app.spawn((2u32,));
+        let c = app.spawn((3u32,));
+
+        app.despawn(a).unwrap();
+        assert!(matches!(app.despawn(a), Err(Error::MissingEntity(entity)) if entity == a));
+        assert!(app.get::<u32>(a).is_err());
+        assert_eq!(*app.get::<u32>(c).unwrap(), 3);
+        assert_eq!(
+            app.get_entity::<u32>().unwrap().iter().collect::<Vec<_>>(),
+            [&3, &2]
+        );
+
+        let d = app.spawn((4u32,));
+        assert_eq!(d.index(), a.index());
+        assert!(app.get::<u32>(a).is_err());
+        assert_eq!(*app.get::<u32>(d).unwrap(), 4);
+        assert_eq!(*app.get::<u32>(b).unwrap(), 2);
+        assert!(app.get::<i32>(b).is_err());
+
+        assert!(matches!(
+            app.spawn_at(b, (5u32,)),
+            Err(Error::EntityExists(_))
+        ));
+        let restored = Entity::from_raw(10, 3);
+        app.spawn_at(restored, (6u32,)).unwrap();
+        assert_eq!(*app.get::<u32>(restored).unwrap(), 6);
+        assert_ne!(app.spawn((7u32,)), restored);
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    error,
+    fmt::{self, Display},
+    io,
+};
+
+use crate::{entity::Entity, schedule::ScheduleError, ModuleId};
+
+/// Everything which can go wrong when accessing an [`App`](crate::App)
+#[derive(Debug)]
+pub enum Error {
+    /// No module is registered with this id
+    MissingModule(ModuleId),
+    /// No entity has ever had a component of this type, or the entity looked up does not
+    /// have one
+    MissingComponent(&'static str),
+    /// The entity handle is stale
+    MissingEntity(Entity),
+    /// An entity with the same index is alive
+    EntityExists(Entity),
+    /// The entity cannot be attached to itself or one of its descendants
+    HierarchyCycle(Entity),
+    /// [`App::add_event`](crate::App::add_event) was never called for this event type
+    MissingEvent(&'static str),
+    /// A lock on the named module or component is held elsewhere and taking it would block
+    WouldBlock(&'static str),
+    /// A stored value was not of the type it was looked up as
+    TypeMismatch(&'static str),
+    /// Nothing is stored under this key
+    MissingKey(String),
+    /// The named prefab extends itself, directly or through others
+    PrefabCycle(String),
+    /// A task spawned on the background pool panicked, with this message
+    TaskPanicked(String),
+    /// A plugin of this type has already been added
+    DuplicatePlugin(&'static str),
+    /// A plugin was added before one it requires
+    MissingPlugin {
+        plugin: &'static str,
+        required: &'static str,
+    },
+    /// A plugin could not set up what it provides, such as a window
+    PluginFailed {
+        plugin: &'static str,
+        source: Box<dyn error::Error + Send + Sync>,
+    },
+    Schedule(ScheduleError),
+    Io(io::Error),
+    Serialization(Box<dyn error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Wraps the failure of a plugin's [`build`](crate::plugin::Plugin::build)
+    pub fn plugin_failed(
+        plugin: &'static str,
+        err: impl Into<Box<dyn error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::PluginFailed {
+            plugin,
+            source: err.into(),
+        }
+    }
+
+    /// Wraps any (de)serialization failure, such as a `serde_json::Error`
+    pub fn serialization(err: impl Into<Box<dyn error::Error + Send + Sync>>) -> Self {
+        Self::Serialization(err.into())
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingModule(module) => write!(f, "{module} is not registered"),
+            Self::MissingComponent(name) => write!(f, "no component of type {name}"),
+            Self::MissingEntity(entity) => write!(
+                f,
+                "entity {}v{} does not exist",
+                entity.index(),
+                entity.generation()
+            ),
+            Self::EntityExists(entity) => {
+                write!(f, "entity index {} is already in use", entity.index())
+            }
+            Self::HierarchyCycle(entity) => write!(
+                f,
+                "entity {}v{} cannot be a child of itself or its descendants",
+                entity.index(),
+                entity.generation()
+            ),
+            Self::MissingEvent(name) => write!(f, "{name} was never added as an event"),
+            Self::WouldBlock(name) => write!(f, "{name} is locked elsewhere"),
+            Self::TypeMismatch(name) => write!(f, "stored value is not a {name}"),
+            Self::MissingKey(key) => write!(f, "nothing is stored under {key:?}"),
+            Self::PrefabCycle(name) => write!(f, "prefab {name:?} extends itself"),
+            Self::TaskPanicked(message) => write!(f, "task panicked: {message}"),
+            Self::DuplicatePlugin(name) => write!(f, "{name} has already been added"),
+            Self::MissingPlugin { plugin, required } => {
+                write!(f, "{plugin} requires {required}, which has not been added")
+            }
+            Self::PluginFailed { plugin, source } => write!(f, "{plugin} failed: {source}"),
+            Self::Schedule(err) => write!(f, "{err}"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Serialization(err) => write!(f, "serialization failed: {err}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Schedule(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::PluginFailed { source, .. } | Self::Serialization(source) => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
+impl From<ScheduleError> for Error {
+    fn from(err: ScheduleError) -> Self {
+        Self::Schedule(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
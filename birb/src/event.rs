@@ -0,0 +1,174 @@
+use parking_lot::{Mutex, RwLock};
+use std::{any::Any, collections::HashMap, mem};
+
+use crate::{
+    lock::{self, ReadGuard},
+    ModuleId,
+};
+
+/// Every event of one type sent during this tick and the last
+pub(crate) struct Buffers<E> {
+    previous: Vec<E>,
+    current: Vec<E>,
+    /// The id of the first event in `previous`, ids count every event ever sent
+    start: usize,
+}
+
+impl<E> Buffers<E> {
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+    }
+
+    const fn end(&self) -> usize {
+        self.start + self.previous.len() + self.current.len()
+    }
+}
+
+pub(crate) struct Channel<E> {
+    pub buffers: RwLock<Buffers<E>>,
+    /// The id of the next event each reader will see, keyed by the module reading
+    cursors: Mutex<HashMap<Option<ModuleId>, usize>>,
+}
+
+impl<E> Default for Channel<E> {
+    fn default() -> Self {
+        Self {
+            buffers: RwLock::new(Buffers {
+                previous: Vec::new(),
+                current: Vec::new(),
+                start: 0,
+            }),
+            cursors: Mutex::default(),
+        }
+    }
+}
+
+impl<E> Channel<E> {
+    /// Moves the reader's cursor to the end of `buffers`, returning how many of the buffered
+    /// events it had already seen. Run conditions leave the cursor where it is.
+    pub fn read(&self, buffers: &Buffers<E>) -> usize {
+        let reader = lock::current();
+        let mut cursors = self.cursors.lock();
+        let previous = if lock::is_checking() {
+            cursors.get(&reader).copied()
+        } else {
+            cursors.insert(reader, buffers.end())
+        };
+        previous
+            .unwrap_or(buffers.start)
+            .saturating_sub(buffers.start)
+    }
+}
+
+/// Type erased [`Channel`] so that every channel can be swapped at the end of a tick
+pub(crate) trait AnyChannel: Any + Send + Sync {
+    /// Drops the events from the previous tick and starts a new buffer for the next one
+    fn update(&mut self);
+}
+
+impl<E: 'static + Send + Sync> AnyChannel for Channel<E> {
+    fn update(&mut self) {
+        let buffers = self.buffers.get_mut();
+        buffers.start += buffers.previous.len();
+        buffers.previous = mem::take(&mut buffers.current);
+    }
+}
+
+/// The events of one type which the current module has not yet read, kept locked until dropped.
+///
+/// Events stay readable for the tick they are sent in and the one after, so every module sees
+/// each event exactly once no matter which order the modules run in.
+pub struct Events<'a, E> {
+    pub(crate) buffers: ReadGuard<'a, Buffers<E>>,
+    pub(crate) seen: usize,
+}
+
+impl<'a, E> Events<'a, E> {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buffers.end() - self.buffers.start - self.seen
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &E> + use<'_, 'a, E> {
+        let previous = &self.buffers.previous;
+        let current = &self.buffers.current;
+        previous[self.seen.min(previous.len())..]
+            .iter()
+            .chain(&current[self.seen.saturating_sub(previous.len())..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{schedule::Schedule, App, Error, Module};
+
+    #[derive(Debug, PartialEq)]
+    struct Ping(u32);
+
+    /// Records every ping it reads
+    #[derive(Debug, Default)]
+    struct Reader<const N: usize>(Vec<u32>);
+
+    impl<const N: usize> Module for Reader<N> {
+        fn tick(&mut self, app: &App) {
+            self.0
+                .extend(app.events::<Ping>().unwrap().iter().map(|ping| ping.0));
+        }
+    }
+
+    #[derive(Debug)]
+    struct Sender(u32);
+
+    impl Module for Sender {
+        fn tick(&mut self, app: &App) {
+            app.send(Ping(self.0)).unwrap();
+            self.0 += 1;
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().after::<Reader<0>>().before::<Reader<1>>()
+        }
+    }
+
+    /// Records every ping it reads, only running when there are any
+    #[derive(Debug, Default)]
+    struct Gated(Vec<u32>);
+
+    impl Module for Gated {
+        fn tick(&mut self, app: &App) {
+            self.0
+                .extend(app.events::<Ping>().unwrap().iter().map(|ping| ping.0));
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new()
+                .after::<Sender>()
+                .run_if(|app| !app.events::<Ping>().unwrap().is_empty())
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let mut app = App::new();
+        app.add_event::<Ping>();
+        app.register_module(Reader::<0>::default()).unwrap();
+        app.register_module(Reader::<1>::default()).unwrap();
+        app.register_module(Sender(0)).unwrap();
+        app.register_module(Gated::default()).unwrap();
+        for _ in 0..3 {
+            app.tick();
+        }
+
+        assert_eq!(app.get_module::<Reader<0>>().unwrap().0, [0, 1]);
+        assert_eq!(app.get_module::<Reader<1>>().unwrap().0, [0, 1, 2]);
+        assert_eq!(app.get_module::<Gated>().unwrap().0, [0, 1, 2]);
+        assert_eq!(app.events::<Ping>().unwrap().len(), 1);
+        assert!(app.events::<Ping>().unwrap().is_empty());
+        assert!(matches!(app.send(0u8), Err(Error::MissingEvent(_))));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{entity::Entity, App, Error};
+
+/// The entity an entity is attached to, set with [`App::set_parent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Parent {
+    #[must_use]
+    pub const fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// The entities attached to an entity, in the order they were added. Despawning an entity
+/// despawns all of its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    #[must_use]
+    pub fn get(&self) -> &[Entity] {
+        &self.0
+    }
+}
+
+impl App {
+    /// Attaches `child` to `parent`, detaching it from its old parent if it had one
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if either handle is stale, or
+    /// [`Error::HierarchyCycle`] if `parent` is `child` or one of its descendants
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), Error> {
+        if self.entities.get(child).is_none() {
+            return Err(Error::MissingEntity(child));
+        }
+        if self.entities.get(parent).is_none() {
+            return Err(Error::MissingEntity(parent));
+        }
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                return Err(Error::HierarchyCycle(child));
+            }
+            ancestor = self.parent(entity);
+        }
+
+        self.remove_parent(child)?;
+        self.insert(child, (Parent(parent),))?;
+        if self.contains::<Children>(parent) {
+            self.get_mut::<Children>(parent)?.0.push(child);
+        } else {
+            self.insert(parent, (Children(vec![child]),))?;
+        }
+        Ok(())
+    }
+
+    /// Detaches `child` from its parent, returning the parent if it had one. A parent which
+    /// has been despawned, or has lost its [`Children`], is still returned.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale
+    pub fn remove_parent(&mut self, child: Entity) -> Result<Option<Entity>, Error> {
+        if self.entities.get(child).is_none() {
+            return Err(Error::MissingEntity(child));
+        }
+        let Some(parent) = self.parent(child) else {
+            return Ok(None);
+        };
+        self.remove::<Parent>(child)?;
+        let empty = match self.get_mut::<Children>(parent) {
+            Ok(mut children) => {
+                children.0.retain(|&entity| entity != child);
+                children.0.is_empty()
+            }
+            // The child is already detached from its parent's side
+            Err(_) => false,
+        };
+        if empty {
+            self.remove::<Children>(parent)?;
+        }
+        Ok(Some(parent))
+    }
+
+    /// The entity `child` is attached to, or `None` if it has no parent or the handle is stale
+    #[must_use]
+    pub fn parent(&self, child: Entity) -> Option<Entity> {
+        self.get::<Parent>(child).ok().map(|parent| parent.get())
+    }
+
+    /// A copy of the entities attached to `parent`, empty if it has none or the handle is
+    /// stale
+    #[must_use]
+    pub fn children(&self, parent: Entity) -> Vec<Entity> {
+        self.get::<Children>(parent)
+            .map(|children| children.0.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Children, Parent};
+    use crate::{App, Error};
+
+    #[test]
+    fn test_hierarchy() {
+        let mut app = App::new();
+        let tank = app.spawn((0u32,));
+        let turret = app.spawn((1u32,));
+        let barrel = app.spawn((2u32,));
+        let other = app.spawn((3u32,));
+        app.set_parent(turret, tank).unwrap();
+        app.set_parent(barrel, turret).unwrap();
+        assert_eq!(app.children(tank), [turret]);
+        assert_eq!(app.parent(barrel), Some(turret));
+        assert!(matches!(
+            app.set_parent(tank, barrel),
+            Err(Error::HierarchyCycle(_))
+        ));
+
+        app.set_parent(barrel, other).unwrap();
+        assert!(app.children(turret).is_empty());
+        app.set_parent(barrel, turret).unwrap();
+        assert!(app.children(other).is_empty());
+
+        app.despawn(turret).unwrap();
+        assert!(app.children(tank).is_empty());
+        assert!(app.get::<u32>(barrel).is_err());
+        app.despawn(tank).unwrap();
+        assert_eq!(*app.get::<u32>(other).unwrap(), 3);
+
+        // Children whose parent has lost track of them can still be despawned
+        let orphan = app.spawn((4u32,));
+        app.set_parent(orphan, other).unwrap();
+        app.remove::<Children>(other).unwrap();
+        app.despawn(orphan).unwrap();
+        let ghost = app.spawn((5u32,));
+        app.despawn(ghost).unwrap();
+        let orphan = app.spawn((6u32, Parent(ghost)));
+        assert_eq!(app.remove_parent(orphan).unwrap(), Some(ghost));
+        app.insert(orphan, (Parent(ghost),)).unwrap();
+        app.despawn(orphan).unwrap();
+    }
+}
@@ -1,33 +1,160 @@
 #![deny(clippy::all)]
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
-#![feature(downcast_unchecked)]
 
-use list_any::VecAny;
-use parking_lot::{
-    lock_api::{MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLockReadGuard, RwLockWriteGuard},
-    RawRwLock, RwLock,
+use archetype::Archetypes;
+use command::{Command, Commands};
+use component::{
+    Bundle, BundleWriter, Component, ComponentInfo, Components, ComponentsMut, DynamicComponent,
+    Ticks,
 };
+use entity::{Entities, Entity, Location};
+pub use error::Error;
+use event::{AnyChannel, Channel, Events};
+use list_any::VecAny;
+use lock::{ReadGuard, WriteGuard};
+use observer::{Observers, Trigger};
+use parking_lot::{Mutex, RwLock};
+use plugin::{Plugin, PluginId};
+use profile::{Frame, Profiler, Sample};
+use query::{Query, QueryData, QueryFilter};
 use rayon::prelude::*;
+use schedule::{Node, Plan, Schedule, ScheduleError, Stage};
+use state::{State, States};
 use std::{
-    any::{Any, TypeId},
+    any::{type_name, Any, TypeId},
+    cmp::Reverse,
     collections::HashMap,
-    fmt::Debug,
+    fmt::{self, Debug, Display},
+    future::Future,
+    mem,
     ops::{Deref, DerefMut},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
 };
+use task::{CatchUnwind, Deliver, Delivery, Shared, Task, TaskPool};
+use timestep::{FixedTime, RunMode};
+
+mod archetype;
+pub mod command;
+pub mod component;
+pub mod entity;
+pub mod error;
+pub mod event;
+pub mod hierarchy;
+pub mod lock;
+pub mod observer;
+pub mod plugin;
+pub mod profile;
+pub mod query;
+pub mod schedule;
+pub mod state;
+pub mod task;
+pub mod timestep;
+
+/// Identifies a registered module by its type and, for modules registered with
+/// [`App::register_module_as`], its label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModuleId {
+    type_id: TypeId,
+    name: &'static str,
+    label: Option<&'static str>,
+}
+
+impl ModuleId {
+    /// The unlabelled instance of `T`
+    #[must_use]
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+            label: None,
+        }
+    }
+
+    #[must_use]
+    pub fn labelled<T: 'static>(label: &'static str) -> Self {
+        Self {
+            label: Some(label),
+            ..Self::of::<T>()
+        }
+    }
+
+    #[must_use]
+    pub const fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The name of the module's type
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    #[must_use]
+    pub const fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+}
+
+impl Display for ModuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(label) => write!(f, "{}#{label}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
 
 pub trait Module: Any + Debug + Send + Sync {
+    /// Runs once before the module's first tick. The module is taken out of the app while this
+    /// runs, so it cannot look itself up.
+    fn init(&mut self, _: &mut App) {}
+
     fn tick(&mut self, _: &App) {}
+
+    /// Runs once the app stops, in the reverse of the order modules tick in
+    fn shutdown(&mut self, _: &App) {}
+
+    /// Where this module runs within a tick, queried once when the module is registered
+    fn schedule(&self) -> Schedule {
+        Schedule::default()
+    }
 }
 
 pub trait MainThreadModule: Any + Debug {
+    /// Runs once before the module's first tick. The module is taken out of the app while this
+    /// runs, so it cannot look itself up.
+    fn init(&mut self, _: &mut MainThreadApp) {}
+
     fn tick(&mut self, _: &MainThreadApp) {}
+
+    /// Runs once the app stops, after every [`Module::shutdown`], in the reverse of the order
+    /// main thread modules were registered in
+    fn shutdown(&mut self, _: &MainThreadApp) {}
+}
+
+struct MainThreadEntry {
+    id: ModuleId,
+    order: usize,
+    initialized: bool,
+    last_run: AtomicU64,
+    module: Box<RwLock<dyn MainThreadModule>>,
 }
 
 #[derive(Default)]
 pub struct MainThreadApp {
     app: App,
-    modules: HashMap<TypeId, Box<RwLock<dyn MainThreadModule>>>,
+    modules: HashMap<TypeId, MainThreadEntry>,
+    /// The module whose init hook is running, taken out of `modules` until it returns
+    initializing: Option<TypeId>,
+    registered: usize,
+    plugins: Vec<PluginId>,
+    run_mode: RunMode,
 }
 
 impl MainThreadApp {
@@ -36,29 +163,200 @@ impl MainThreadApp {
         Self::default()
     }
 
-    pub fn register_main_thread_module<T: 'static + MainThreadModule>(&mut self, module: T) {
-        self.modules
-            .insert(TypeId::of::<T>(), Box::new(RwLock::new(module)));
+    /// Registers a module which ticks on the main thread, before the other modules
+    ///
+    /// # Errors
+    /// Returns an error, and leaves the app unchanged, if a `T` is already registered
+    pub fn register_main_thread_module<T: 'static + MainThreadModule>(
+        &mut self,
+        module: T,
+    ) -> Result<(), Error> {
+        let id = ModuleId::of::<T>();
+        let key = TypeId::of::<T>();
+        if self.modules.contains_key(&key) || self.initializing == Some(key) {
+            return Err(ScheduleError::Duplicate(id).into());
+        }
+        let entry = MainThreadEntry {
+            id,
+            order: self.registered,
+            initialized: false,
+            last_run: AtomicU64::new(0),
+            module: Box::new(RwLock::new(module)),
+        };
+        self.modules.insert(TypeId::of::<T>(), entry);
+        self.registered += 1;
+        Ok(())
     }
 
-    pub fn register<T, F: FnOnce(&mut T)>(&mut self, func: F)
+    pub fn register<T, R, F: FnOnce(&mut T) -> R>(&mut self, func: F) -> R
     where
         for<'a> &'a mut T: From<&'a mut Self>,
     {
-        func(self.into());
+        func(self.into())
     }
 
-    pub fn tick(&self) {
-        self.modules
+    /// Builds `plugin`, after checking that every plugin it requires has already been added
+    ///
+    /// # Errors
+    /// Returns [`Error::DuplicatePlugin`] if a plugin of the same type has already been added,
+    /// [`Error::MissingPlugin`] if one it requires has not been, or any error from
+    /// [`Plugin::build`]
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> Result<(), Error> {
+        let id = PluginId::of::<P>();
+        if self.plugins.contains(&id) {
+            return Err(Error::DuplicatePlugin(plugin.name()));
+        }
+        if let Some(required) = plugin
+            .requires()
+            .into_iter()
+            .find(|required| !self.plugins.contains(required))
+        {
+            return Err(Error::MissingPlugin {
+                plugin: plugin.name(),
+                required: required.name(),
+            });
+        }
+        plugin.build(self)?;
+        self.plugins.push(id);
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn has_plugin<P: Plugin>(&self) -> bool {
+        self.plugins.contains(&PluginId::of::<P>())
+    }
+
+    /// Initialises any main thread modules which have not yet been, in the order they were
+    /// registered
+    fn init(&mut self) {
+        while let Some(id) = self
+            .modules
             .iter()
-            .for_each(|(_, module)| module.write().tick(self));
+            .filter(|(_, entry)| !entry.initialized)
+            .min_by_key(|(_, entry)| entry.order)
+            .map(|(id, _)| *id)
+        {
+            let Some(mut entry) = self.modules.remove(&id) else {
+                break;
+            };
+            self.initializing = Some(id);
+            {
+                let _running = lock::running(entry.id, self.hook_ticks(&entry.last_run));
+                entry.module.get_mut().init(self);
+            }
+            self.initializing = None;
+            entry.initialized = true;
+            self.modules.insert(id, entry);
+        }
+    }
+
+    /// Ticks every main thread module, then every module
+    ///
+    /// # Panics
+    /// Panics if a module registered while initialising modules, or by a command, has an
+    /// invalid schedule
+    pub fn tick(&mut self) {
+        self.tick_main_thread();
         self.app.tick();
     }
 
-    pub fn run(&self) {
+    /// Like [`MainThreadApp::tick`], but as if `delta` had passed since the last tick rather
+    /// than measuring it
+    ///
+    /// # Panics
+    /// Panics if a module registered while initialising modules, or by a command, has an
+    /// invalid schedule
+    pub fn advance(&mut self, delta: Duration) {
+        self.tick_main_thread();
+        self.app.advance(delta);
+    }
+
+    /// Ticks every main thread module, in the order they were registered
+    fn tick_main_thread(&mut self) {
+        self.init();
+        let mut entries: Vec<&MainThreadEntry> = self.modules.values().collect();
+        entries.sort_unstable_by_key(|entry| entry.order);
+        for entry in entries {
+            let _running = lock::running(entry.id, self.run_ticks(&entry.last_run));
+            entry.module.write().tick(self);
+        }
+    }
+
+    /// How [`MainThreadApp::run`] paces ticks
+    pub const fn set_run_mode(&mut self, run_mode: RunMode) {
+        self.run_mode = run_mode;
+    }
+
+    /// Ticks until [`App::exit`] is called, then shuts every module down. Modules are still
+    /// shut down if a tick panics, after which the panic carries on unwinding.
+    pub fn run(&mut self) {
+        self.run_while(|_| true);
+    }
+
+    /// Like [`MainThreadApp::run`], but also stops after `ticks` ticks
+    pub fn run_for_ticks(&mut self, ticks: u64) {
+        let end = self.ticks() + ticks;
+        self.run_while(|app| app.ticks() < end);
+    }
+
+    /// Like [`MainThreadApp::run`], but also stops once [`App::elapsed`] has advanced by
+    /// `duration`. With [`RunMode::Fixed`] this is simulated time, so the number of ticks run
+    /// is the same every time.
+    pub fn run_for(&mut self, duration: Duration) {
+        let end = self.elapsed() + duration;
+        self.run_while(|app| app.elapsed() < end);
+    }
+
+    /// Like [`MainThreadApp::run`], but also stops as soon as `done` returns `true`. It is
+    /// checked before every tick, so nothing runs if it already holds.
+    pub fn run_until(&mut self, mut done: impl FnMut(&App) -> bool) {
+        self.run_while(|app| !done(app));
+    }
+
+    fn run_while(&mut self, mut running: impl FnMut(&App) -> bool) {
         *self.app.running.write() = true;
-        while *self.app.running.read() {
-            self.tick();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut next = Instant::now();
+            while *self.app.running.read() && running(&self.app) {
+                match self.run_mode {
+                    RunMode::Fixed(delta) => self.advance(delta),
+                    RunMode::Unlimited | RunMode::Limited(_) => self.tick(),
+                }
+                self.run_mode.wait(&mut next);
+            }
+        }));
+        self.shutdown();
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Runs [`App::shutdown`], then the shutdown hook of every initialised main thread module.
+    /// Every hook runs even if an earlier one panics, the first panic is resumed afterwards.
+    pub fn shutdown(&mut self) {
+        let mut payload = panic::catch_unwind(AssertUnwindSafe(|| self.app.shutdown())).err();
+
+        let app: &Self = self;
+        let mut entries: Vec<&MainThreadEntry> = app
+            .modules
+            .values()
+            .filter(|entry| entry.initialized)
+            .collect();
+        entries.sort_unstable_by_key(|entry| Reverse(entry.order));
+        for entry in entries {
+            let _running = lock::running(entry.id, app.hook_ticks(&entry.last_run));
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| entry.module.write().shutdown(app)));
+            if let Err(err) = result {
+                payload.get_or_insert(err);
+            }
+        }
+
+        for entry in self.modules.values_mut() {
+            entry.initialized = false;
+        }
+        if let Some(payload) = payload {
+            panic::resume_unwind(payload);
         }
     }
 }
@@ -83,11 +381,45 @@ impl<'a> From<&'a mut MainThreadApp> for &'a mut App {
     }
 }
 
+struct ModuleEntry {
+    id: ModuleId,
+    order: usize,
+    initialized: bool,
+    enabled: bool,
+    last_run: AtomicU64,
+    /// When the module was last accessed through [`App::get_module_mut`] or similar
+    changed: AtomicU64,
+    schedule: Schedule,
+    module: Box<RwLock<dyn Module>>,
+}
+
 #[derive(Default)]
 pub struct App {
-    entities: HashMap<TypeId, RwLock<VecAny>>,
-    modules: HashMap<TypeId, Box<RwLock<dyn Module>>>,
+    archetypes: Archetypes,
+    entities: Entities,
+    modules: HashMap<ModuleId, ModuleEntry>,
+    /// The module whose init hook is running, taken out of `modules` until it returns
+    initializing: Option<ModuleId>,
+    registered: usize,
+    plan: Plan,
     running: RwLock<bool>,
+    commands: Mutex<Vec<Command>>,
+    events: HashMap<TypeId, Box<dyn AnyChannel>>,
+    fixed_time: FixedTime,
+    last_tick: Option<Instant>,
+    ticks: u64,
+    elapsed: Duration,
+    delta: Duration,
+    stepping: bool,
+    task_pool: OnceLock<TaskPool>,
+    /// Tasks whose results have not yet been delivered, with when to deliver them
+    tasks: Mutex<Vec<(Delivery, Arc<dyn Deliver>)>>,
+    /// The most recent change tick handed out, see [`Ticks`]
+    change_tick: AtomicU64,
+    /// Whether a [`Profiler`] is registered, checked once per tick
+    profiling: bool,
+    samples: Mutex<Vec<Sample>>,
+    observers: Observers,
 }
 
 impl App {
@@ -97,101 +429,1280 @@ impl App {
         MainThreadApp::default()
     }
 
+    /// Adds an entity made up of the components in `bundle`, returning a handle which can
+    /// later be used to look it up or despawn it
+    ///
     /// # Panics
-    /// Panics if entities map has a mismatch between the type stated in the key and the type
-    /// stated in the value
-    pub fn register_entity<T: 'static + Sync + Send>(&mut self, entity: T) {
-        let id = TypeId::of::<T>();
-        match self.entities.get_mut(&id) {
-            Some(ents) => ents.write().downcast_mut().unwrap().push(entity),
-            None => {
-                self.entities.insert(id, RwLock::new(vec![entity].into()));
+    /// Panics if the bundle contains the same component twice
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let tick = self.change_ticks().this_run;
+        let index = self.archetypes.get_or_insert(B::components());
+        let archetype = self.archetypes.get_mut(index);
+        let row = archetype.len();
+        bundle.write(&mut BundleWriter::push(archetype, tick));
+        let entity = self.entities.alloc(Location {
+            archetype: index,
+            row,
+        });
+        archetype.entities.push(entity);
+        self.notify_components(Trigger::Add, entity, index);
+        entity
+    }
+
+    /// Spawns an entity with a specific handle, such as one restored from a snapshot
+    ///
+    /// # Errors
+    /// Returns [`Error::EntityExists`] if an entity with the same index is alive
+    ///
+    /// # Panics
+    /// Panics if the bundle contains the same component twice
+    pub fn spawn_at<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Result<(), Error> {
+        if !self.entities.claim(entity) {
+            return Err(Error::EntityExists(entity));
+        }
+        self.spawn_reserved(entity, bundle);
+        Ok(())
+    }
+
+    /// Gives a reserved handle its components
+    fn spawn_reserved<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        let tick = self.change_ticks().this_run;
+        let index = self.archetypes.get_or_insert(B::components());
+        let archetype = self.archetypes.get_mut(index);
+        let row = archetype.len();
+        bundle.write(&mut BundleWriter::push(archetype, tick));
+        self.entities.place(
+            entity,
+            Location {
+                archetype: index,
+                row,
+            },
+        );
+        archetype.entities.push(entity);
+        self.notify_components(Trigger::Add, entity, index);
+    }
+
+    /// Spawns an entity for each bundle, returning their handles in order
+    ///
+    /// # Panics
+    /// Panics if the bundle contains the same component twice
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> Vec<Entity> {
+        self.spawn_batch_with(&[], bundles)
+    }
+
+    /// Spawns an entity for each bundle, also giving each one a clone of every component in
+    /// `shared` whose type is not in the bundle, returning their handles in order. Every
+    /// entity is spawned straight into the same archetype.
+    ///
+    /// # Panics
+    /// Panics if the bundle or `shared` contains the same component twice
+    pub fn spawn_batch_with<B: Bundle>(
+        &mut self,
+        shared: &[DynamicComponent],
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Vec<Entity> {
+        let tick = self.change_ticks().this_run;
+        let mut infos = B::components();
+        let shared: Vec<&DynamicComponent> = shared
+            .iter()
+            .filter(|component| !infos.iter().any(|info| info.id() == component.info().id()))
+            .collect();
+        infos.extend(shared.iter().map(|component| component.info()));
+        let index = self.archetypes.get_or_insert(infos);
+        let archetype = self.archetypes.get_mut(index);
+        let entities: Vec<Entity> = bundles
+            .into_iter()
+            .map(|bundle| {
+                let row = archetype.len();
+                let mut writer = BundleWriter::push(archetype, tick);
+                bundle.write(&mut writer);
+                for component in &shared {
+                    component.write(&mut writer);
+                }
+                let entity = self.entities.alloc(Location {
+                    archetype: index,
+                    row,
+                });
+                archetype.entities.push(entity);
+                entity
+            })
+            .collect();
+        for &entity in &entities {
+            self.notify_components(Trigger::Add, entity, index);
+        }
+        entities
+    }
+
+    /// Runs the observers of `trigger` on every component in an archetype
+    fn notify_components(&self, trigger: Trigger, entity: Entity, archetype: usize) {
+        if !self.observers.is_empty() {
+            let infos = self.archetypes.get(archetype).infos();
+            self.notify(trigger, entity, infos.map(|info| info.id()));
+        }
+    }
+
+    /// Removes an entity and all of its components, along with its children and their
+    /// descendants
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale
+    pub fn despawn(&mut self, entity: Entity) -> Result<(), Error> {
+        self.remove_parent(entity)?;
+        let mut despawned = vec![entity];
+        while let Some(entity) = despawned.pop() {
+            despawned.extend(self.children(entity));
+            let Some(Location { archetype, .. }) = self.entities.get(entity) else {
+                continue;
+            };
+            self.notify_components(Trigger::Remove, entity, archetype);
+            let Some(Location { archetype, row }) = self.entities.free(entity) else {
+                continue;
+            };
+            if let Some(moved) = self.archetypes.get_mut(archetype).swap_remove(row) {
+                self.entities.relocate(moved, Location { archetype, row });
             }
         }
+        Ok(())
     }
 
+    /// Adds the components in `bundle` to an entity, replacing any it already has
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale
+    ///
     /// # Panics
-    /// Panics if entities map has a mismatch between the type stated in the key and the type
-    /// stated in the value
-    pub fn register_entities<T: 'static + Sync + Send + Clone>(&mut self, entities: &[T]) {
+    /// Panics if the bundle contains the same component twice
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Result<(), Error> {
+        let Location { archetype, row } = self
+            .entities
+            .get(entity)
+            .ok_or(Error::MissingEntity(entity))?;
+        let tick = self.change_ticks().this_run;
+        let added = B::components();
+        let mut infos: Vec<ComponentInfo> = self.archetypes.get(archetype).infos().collect();
+        let mut new = Vec::new();
+        for info in &added {
+            if !infos.iter().any(|existing| existing.id() == info.id()) {
+                infos.push(*info);
+                new.push(info.id());
+            }
+        }
+        let target = self.archetypes.get_or_insert(infos);
+
+        if target == archetype {
+            let archetype = self.archetypes.get_mut(archetype);
+            bundle.write(&mut BundleWriter::replace(archetype, row, tick));
+            return Ok(());
+        }
+
+        let replaced: Vec<TypeId> = added.iter().map(ComponentInfo::id).collect();
+        let (from, to) = self.archetypes.pair_mut(archetype, target);
+        let moved = from.move_row(row, to, &replaced, |info, column| {
+            (info.swap_remove)(column, row);
+        });
+        let new_row = to.len();
+        bundle.write(&mut BundleWriter::push(to, tick));
+        to.entities.push(entity);
+
+        self.entities.relocate(
+            entity,
+            Location {
+                archetype: target,
+                row: new_row,
+            },
+        );
+        if let Some(moved) = moved {
+            self.entities.relocate(moved, Location { archetype, row });
+        }
+        self.notify(Trigger::Add, entity, new);
+        Ok(())
+    }
+
+    /// Removes a component from an entity and returns it
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale, or
+    /// [`Error::MissingComponent`] if the entity has no `T`
+    ///
+    /// # Panics
+    /// Panics if the archetype storage has a mismatch between a column's component and the
+    /// type stored in it
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Result<T, Error> {
+        let Location { archetype, row } = self
+            .entities
+            .get(entity)
+            .ok_or(Error::MissingEntity(entity))?;
         let id = TypeId::of::<T>();
-        match self.entities.get_mut(&id) {
-            Some(ents) => ents
-                .write()
-                .downcast_mut()
-                .unwrap()
-                .extend_from_slice(entities),
-            None => {
-                self.entities
-                    .insert(id, RwLock::new(entities.to_vec().into()));
+        if !self.archetypes.get(archetype).contains(id) {
+            return Err(Error::MissingComponent(type_name::<T>()));
+        }
+        self.notify(Trigger::Remove, entity, [id]);
+        let infos = self
+            .archetypes
+            .get(archetype)
+            .infos()
+            .filter(|info| info.id() != id)
+            .collect();
+        let target = self.archetypes.get_or_insert(infos);
+
+        let mut removed = None;
+        let (from, to) = self.archetypes.pair_mut(archetype, target);
+        let moved = from.move_row(row, to, &[], |_, column| {
+            removed = Some(column.downcast_mut::<T>().unwrap().swap_remove(row));
+        });
+        let new_row = to.len();
+        to.entities.push(entity);
+
+        self.entities.relocate(
+            entity,
+            Location {
+                archetype: target,
+                row: new_row,
+            },
+        );
+        if let Some(moved) = moved {
+            self.entities.relocate(moved, Location { archetype, row });
+        }
+        Ok(removed.unwrap())
+    }
+
+    /// Returns `true` if the entity is alive and has a `T` component
+    #[must_use]
+    pub fn contains<T: Component>(&self, entity: Entity) -> bool {
+        self.entities.get(entity).is_some_and(|location| {
+            self.archetypes
+                .get(location.archetype)
+                .contains(TypeId::of::<T>())
+        })
+    }
+
+    /// Spawns an entity with the single component `entity`
+    pub fn register_entity<T: Component>(&mut self, entity: T) {
+        self.spawn((entity,));
+    }
+
+    /// Spawns an entity with a single component for each of `entities`
+    pub fn register_entities<T: Component + Clone>(&mut self, entities: &[T]) {
+        self.spawn_batch(entities.iter().map(|entity| (entity.clone(),)));
+    }
+
+    /// Registers `module` as the unlabelled instance of `T`
+    ///
+    /// # Errors
+    /// Returns an error, and leaves the app unchanged, if an unlabelled `T` is already
+    /// registered or if the module's [`Schedule`] contradicts those of the already registered
+    /// modules
+    pub fn register_module<T: 'static + Module>(&mut self, module: T) -> Result<(), Error> {
+        self.insert_module(ModuleId::of::<T>(), module)
+    }
+
+    /// Registers `module` under `label`, so that several instances of the same type can be
+    /// registered side by side. Ordering constraints naming `T` apply to every instance.
+    ///
+    /// # Errors
+    /// Returns an error, and leaves the app unchanged, if a `T` with the same label is already
+    /// registered or if the module's [`Schedule`] contradicts those of the already registered
+    /// modules
+    pub fn register_module_as<T: 'static + Module>(
+        &mut self,
+        label: &'static str,
+        module: T,
+    ) -> Result<(), Error> {
+        self.insert_module(ModuleId::labelled::<T>(label), module)
+    }
+
+    fn insert_module<T: 'static + Module>(&mut self, id: ModuleId, module: T) -> Result<(), Error> {
+        if self.modules.contains_key(&id) || self.initializing == Some(id) {
+            return Err(ScheduleError::Duplicate(id).into());
+        }
+        let entry = ModuleEntry {
+            id,
+            order: self.registered,
+            initialized: false,
+            enabled: true,
+            last_run: AtomicU64::new(0),
+            changed: AtomicU64::new(0),
+            schedule: module.schedule(),
+            module: Box::new(RwLock::new(module)),
+        };
+        self.modules.insert(id, entry);
+
+        match self.build_plan() {
+            Ok(plan) => {
+                self.plan = plan;
+                self.registered += 1;
+                Ok(())
+            }
+            Err(err) => {
+                self.modules.remove(&id);
+                Err(err.into())
             }
         }
     }
 
-    pub fn register_module<T: 'static + Module>(&mut self, module: T) {
+    /// Unregisters a module without running any hooks, returning it along with whether it had
+    /// been initialised
+    fn take_module<T: 'static + Module>(&mut self, id: ModuleId) -> Result<(Box<T>, bool), Error> {
+        let entry = self.modules.remove(&id).ok_or(Error::MissingModule(id))?;
+        if let Ok(plan) = self.build_plan() {
+            self.plan = plan;
+        }
+
+        let raw = Box::into_raw(entry.module);
+        // SAFETY: modules are keyed by the `TypeId` of their concrete type, so the module is a
+        // `T`, and casting away the vtable leaves a pointer to the `RwLock<T>` it was boxed as
+        let module = unsafe { Box::from_raw(raw.cast::<RwLock<T>>()) };
+        Ok((Box::new(module.into_inner()), entry.initialized))
+    }
+
+    fn shutdown_removed<T: 'static + Module>(&mut self, id: ModuleId) -> Result<Box<T>, Error> {
+        let (mut module, initialized) = self.take_module::<T>(id)?;
+        if initialized {
+            let _running = lock::running(id, self.change_ticks());
+            module.shutdown(self);
+        }
+        Ok(module)
+    }
+
+    /// Unregisters the unlabelled `T` and returns it, running its shutdown hook first if it
+    /// has been initialised
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no unlabelled `T` is registered
+    pub fn remove_module<T: 'static + Module>(&mut self) -> Result<Box<T>, Error> {
+        self.shutdown_removed(ModuleId::of::<T>())
+    }
+
+    /// Like [`App::remove_module`], for the `T` registered under `label`
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no `T` is registered under `label`
+    pub fn remove_module_as<T: 'static + Module>(
+        &mut self,
+        label: &'static str,
+    ) -> Result<Box<T>, Error> {
+        self.shutdown_removed(ModuleId::labelled::<T>(label))
+    }
+
+    /// Swaps the unlabelled `Old` for the module returned by `replace`, which is handed the
+    /// old module so that it can carry over its state. No shutdown hook runs for the old
+    /// module, the new one is initialised before its first tick as usual.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`], without calling `replace`, if no `Old` is registered.
+    /// Returns an error if the new module cannot be registered, in which case the old module
+    /// stays registered, keeping any changes `replace` made to it.
+    pub fn replace_module<Old: 'static + Module, New: 'static + Module>(
+        &mut self,
+        replace: impl FnOnce(&mut Old) -> New,
+    ) -> Result<(), Error> {
+        self.swap_module(ModuleId::of::<Old>(), ModuleId::of::<New>(), replace)
+    }
+
+    /// Like [`App::replace_module`], for the `Old` registered under `label`. The new module is
+    /// registered under the same label.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`], without calling `replace`, if no `Old` is registered
+    /// under `label`. Returns an error if the new module cannot be registered, in which case
+    /// the old module stays registered, keeping any changes `replace` made to it.
+    pub fn replace_module_as<Old: 'static + Module, New: 'static + Module>(
+        &mut self,
+        label: &'static str,
+        replace: impl FnOnce(&mut Old) -> New,
+    ) -> Result<(), Error> {
+        self.swap_module(
+            ModuleId::labelled::<Old>(label),
+            ModuleId::labelled::<New>(label),
+            replace,
+        )
+    }
+
+    fn swap_module<Old: 'static + Module, New: 'static + Module>(
+        &mut self,
+        old: ModuleId,
+        new: ModuleId,
+        replace: impl FnOnce(&mut Old) -> New,
+    ) -> Result<(), Error> {
+        let entry = self
+            .modules
+            .get_mut(&old)
+            .ok_or(Error::MissingModule(old))?;
+        let module = (entry.module.get_mut() as &mut dyn Any)
+            .downcast_mut()
+            .ok_or(Error::TypeMismatch(type_name::<Old>()))?;
+        let module = replace(module);
+
+        // The old entry is put back as it was if the new module is rejected, and the plan is
+        // only rebuilt once the new module is in
+        let entry = self.modules.remove(&old).ok_or(Error::MissingModule(old))?;
+        self.insert_module(new, module).inspect_err(|_| {
+            self.modules.insert(old, entry);
+        })
+    }
+
+    /// Pauses or resumes a module without unregistering it. A disabled module keeps its place
+    /// in the schedule and its state, but its tick is skipped.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if `module` is not registered
+    pub fn set_enabled(&mut self, module: ModuleId, enabled: bool) -> Result<(), Error> {
         self.modules
-            .insert(TypeId::of::<T>(), Box::new(RwLock::new(module)));
+            .get_mut(&module)
+            .ok_or(Error::MissingModule(module))?
+            .enabled = enabled;
+        Ok(())
     }
 
+    /// Whether `module` is enabled, see [`App::set_enabled`]. A module which is enabled may
+    /// still be skipped by its run conditions.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if `module` is not registered
+    pub fn is_enabled(&self, module: ModuleId) -> Result<bool, Error> {
+        Ok(self.module_entry(module)?.enabled)
+    }
+
+    fn build_plan(&self) -> Result<Plan, ScheduleError> {
+        let mut nodes: Vec<(usize, Node)> = self
+            .modules
+            .iter()
+            .map(|(id, entry)| {
+                let node = Node {
+                    id: *id,
+                    schedule: &entry.schedule,
+                };
+                (entry.order, node)
+            })
+            .collect();
+        nodes.sort_unstable_by_key(|(order, _)| *order);
+        Plan::build(&nodes.into_iter().map(|(_, node)| node).collect::<Vec<_>>())
+    }
+
+    /// Every `T` component
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingComponent`] if no entity has ever had a `T`, or
+    /// [`Error::TypeMismatch`] if the archetype storage has a mismatch between a column's
+    /// component and the type stored in it
+    ///
     /// # Panics
-    /// Panics if entities map has a mismatch between the type stated in the key and the type
-    /// stated in the value
-    #[must_use]
-    pub fn get_entity<T: 'static + Send + Sync>(
-        &self,
-    ) -> Option<MappedRwLockReadGuard<'_, RawRwLock, [T]>> {
-        self.entities.get(&TypeId::of::<T>()).map(|entities| {
-            RwLockReadGuard::map(entities.read(), |entities| {
-                entities.downcast_slice().unwrap()
+    /// Panics in debug builds if taking a lock would deadlock
+    pub fn get_entity<T: Component>(&self) -> Result<Components<'_, T>, Error> {
+        let archetypes = self
+            .archetypes
+            .containing(TypeId::of::<T>())
+            .ok_or(Error::MissingComponent(type_name::<T>()))?;
+        let columns = archetypes
+            .map(|archetype| {
+                let column = archetype
+                    .column(TypeId::of::<T>())
+                    .ok_or(Error::MissingComponent(type_name::<T>()))?;
+                ReadGuard::new(&column.data, column.info.name(), |column| column)
+                    .try_map(VecAny::downcast_slice)
+                    .ok_or(Error::TypeMismatch(type_name::<T>()))
             })
-        })
+            .collect::<Result<_, _>>()?;
+        Ok(Components { columns })
     }
 
+    /// Every `T` component, all of which are marked as changed
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingComponent`] if no entity has ever had a `T`, or
+    /// [`Error::TypeMismatch`] if the archetype storage has a mismatch between a column's
+    /// component and the type stored in it
+    ///
     /// # Panics
-    /// Panics if entities map has a mismatch between the type stated in the key and the type
-    /// stated in the value
+    /// Panics in debug builds if taking a lock would deadlock
+    pub fn get_entity_mut<T: Component>(&self) -> Result<ComponentsMut<'_, T>, Error> {
+        let tick = self.change_ticks().this_run;
+        let archetypes = self
+            .archetypes
+            .containing(TypeId::of::<T>())
+            .ok_or(Error::MissingComponent(type_name::<T>()))?;
+        let columns = archetypes
+            .map(|archetype| {
+                let column = archetype
+                    .column(TypeId::of::<T>())
+                    .ok_or(Error::MissingComponent(type_name::<T>()))?;
+                let guard = WriteGuard::new(&column.data, column.info.name(), |column| column)
+                    .try_map(VecAny::downcast_slice_mut)
+                    .ok_or(Error::TypeMismatch(type_name::<T>()))?;
+                column.mark_all(tick);
+                Ok(guard)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(ComponentsMut { columns })
+    }
+
+    /// Every entity with the components fetched by `Q` which also matches the filter `F`, for
+    /// example `app.query::<(&mut Position, &Velocity), Without<Frozen>>()`. Pass `()` as the
+    /// filter to visit every entity.
+    ///
+    /// Each matched column is locked for reading or writing, as `Q` requires, until the query
+    /// is dropped. Components fetched through `&mut T` are marked as changed as the query
+    /// visits them.
+    ///
+    /// # Panics
+    /// Panics if `Q` accesses the same component more than once, or in debug builds if taking
+    /// a lock would deadlock
     #[must_use]
-    pub fn get_entity_mut<T: 'static + Send + Sync>(
+    pub fn query<Q: QueryData, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        Query::new(&self.archetypes, self.change_ticks())
+    }
+
+    /// Looks up one component of a single entity
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale, [`Error::MissingComponent`] if
+    /// the entity has no `T`, or [`Error::TypeMismatch`] if the archetype storage has a
+    /// mismatch between a column's component and the type stored in it
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn get<T: Component>(&self, entity: Entity) -> Result<ReadGuard<'_, T>, Error> {
+        let Location { archetype, row } = self
+            .entities
+            .get(entity)
+            .ok_or(Error::MissingEntity(entity))?;
+        let column = self
+            .archetypes
+            .get(archetype)
+            .column(TypeId::of::<T>())
+            .ok_or(Error::MissingComponent(type_name::<T>()))?;
+        ReadGuard::new(&column.data, column.info.name(), |column| column)
+            .try_map(|column| column.downcast_slice().map(|slice| &slice[row]))
+            .ok_or(Error::TypeMismatch(type_name::<T>()))
+    }
+
+    /// Looks up one component of a single entity, marking it as changed
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale, [`Error::MissingComponent`] if
+    /// the entity has no `T`, or [`Error::TypeMismatch`] if the archetype storage has a
+    /// mismatch between a column's component and the type stored in it
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn get_mut<T: Component>(&self, entity: Entity) -> Result<WriteGuard<'_, T>, Error> {
+        let Location { archetype, row } = self
+            .entities
+            .get(entity)
+            .ok_or(Error::MissingEntity(entity))?;
+        let column = self
+            .archetypes
+            .get(archetype)
+            .column(TypeId::of::<T>())
+            .ok_or(Error::MissingComponent(type_name::<T>()))?;
+        let guard = WriteGuard::new(&column.data, column.info.name(), |column| column)
+            .try_map(|column| column.downcast_slice_mut().map(|slice| &mut slice[row]))
+            .ok_or(Error::TypeMismatch(type_name::<T>()))?;
+        column.mark(row, self.change_ticks().this_run);
+        Ok(guard)
+    }
+
+    /// Whether `entity`'s `T` has been mutably accessed since the calling module last ran.
+    /// Outside of a module, whether it has ever been.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEntity`] if the handle is stale, or [`Error::MissingComponent`]
+    /// if the entity has no `T`
+    pub fn is_changed<T: Component>(&self, entity: Entity) -> Result<bool, Error> {
+        let Location { archetype, row } = self
+            .entities
+            .get(entity)
+            .ok_or(Error::MissingEntity(entity))?;
+        let column = self
+            .archetypes
+            .get(archetype)
+            .column(TypeId::of::<T>())
+            .ok_or(Error::MissingComponent(type_name::<T>()))?;
+        Ok(self.change_ticks().is_changed(column.changed_at(row)))
+    }
+
+    fn read_module<T: 'static>(entry: &ModuleEntry) -> Result<ReadGuard<'_, T>, Error> {
+        ReadGuard::new(&*entry.module, entry.id.name(), |module| module)
+            .try_map(|module| (module as &dyn Any).downcast_ref())
+            .ok_or(Error::TypeMismatch(type_name::<T>()))
+    }
+
+    fn write_module<'a, T: 'static>(
         &self,
-    ) -> Option<MappedRwLockWriteGuard<'_, RawRwLock, [T]>> {
-        self.entities.get(&TypeId::of::<T>()).map(|entities| {
-            RwLockWriteGuard::map(entities.write(), |entities| {
-                entities.downcast_slice_mut().unwrap()
-            })
+        entry: &'a ModuleEntry,
+    ) -> Result<WriteGuard<'a, T>, Error> {
+        let guard = WriteGuard::new(&*entry.module, entry.id.name(), |module| module)
+            .try_map(|module| (module as &mut dyn Any).downcast_mut())
+            .ok_or(Error::TypeMismatch(type_name::<T>()))?;
+        entry
+            .changed
+            .store(self.change_ticks().this_run, Ordering::Relaxed);
+        Ok(guard)
+    }
+
+    fn module_entry(&self, id: ModuleId) -> Result<&ModuleEntry, Error> {
+        self.modules.get(&id).ok_or(Error::MissingModule(id))
+    }
+
+    /// The unlabelled instance of `T`
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no unlabelled `T` is registered
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn get_module<T: 'static>(&self) -> Result<ReadGuard<'_, T>, Error> {
+        Self::read_module(self.module_entry(ModuleId::of::<T>())?)
+    }
+
+    /// The unlabelled instance of `T`, which is marked as changed. A module changing its own
+    /// state while ticking does not count.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no unlabelled `T` is registered
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn get_module_mut<T: 'static>(&self) -> Result<WriteGuard<'_, T>, Error> {
+        self.write_module(self.module_entry(ModuleId::of::<T>())?)
+    }
+
+    /// The `T` registered under `label`
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no `T` is registered under `label`
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn get_module_as<T: 'static>(
+        &self,
+        label: &'static str,
+    ) -> Result<ReadGuard<'_, T>, Error> {
+        Self::read_module(self.module_entry(ModuleId::labelled::<T>(label))?)
+    }
+
+    /// The `T` registered under `label`
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no `T` is registered under `label`
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn get_module_mut_as<T: 'static>(
+        &self,
+        label: &'static str,
+    ) -> Result<WriteGuard<'_, T>, Error> {
+        self.write_module(self.module_entry(ModuleId::labelled::<T>(label))?)
+    }
+
+    /// Every registered `T` in registration order
+    fn instances<T: 'static>(&self) -> Vec<&ModuleEntry> {
+        let mut entries: Vec<&ModuleEntry> = self
+            .modules
+            .values()
+            .filter(|entry| entry.id.type_id() == TypeId::of::<T>())
+            .collect();
+        entries.sort_unstable_by_key(|entry| entry.order);
+        entries
+    }
+
+    /// Every instance of `T` along with its label, in registration order. Each module is only
+    /// locked once the iterator reaches it.
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking a lock would deadlock
+    pub fn modules_of<T: 'static>(
+        &self,
+    ) -> impl Iterator<Item = Result<(Option<&'static str>, ReadGuard<'_, T>), Error>> {
+        self.instances::<T>()
+            .into_iter()
+            .map(|entry| Ok((entry.id.label(), Self::read_module(entry)?)))
+    }
+
+    /// Every instance of `T` along with its label, in registration order. Each module is only
+    /// locked once the iterator reaches it.
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking a lock would deadlock
+    pub fn modules_of_mut<T: 'static>(
+        &self,
+    ) -> impl Iterator<Item = Result<(Option<&'static str>, WriteGuard<'_, T>), Error>> {
+        self.instances::<T>()
+            .into_iter()
+            .map(|entry| Ok((entry.id.label(), self.write_module(entry)?)))
+    }
+
+    /// Like [`App::get_module`], but fails instead of blocking if the module is being written
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no unlabelled `T` is registered, or
+    /// [`Error::WouldBlock`] if the module is locked
+    pub fn try_get_module<T: 'static>(&self) -> Result<ReadGuard<'_, T>, Error> {
+        let entry = self.module_entry(ModuleId::of::<T>())?;
+        ReadGuard::try_new(&*entry.module, entry.id.name(), |module| module)
+            .ok_or(Error::WouldBlock(entry.id.name()))?
+            .try_map(|module| (module as &dyn Any).downcast_ref())
+            .ok_or(Error::TypeMismatch(type_name::<T>()))
+    }
+
+    /// Like [`App::get_module_mut`], but fails instead of blocking if the module is in use,
+    /// including by the module calling this from its own tick
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no unlabelled `T` is registered, or
+    /// [`Error::WouldBlock`] if the module is locked
+    pub fn try_get_module_mut<T: 'static>(&self) -> Result<WriteGuard<'_, T>, Error> {
+        let entry = self.module_entry(ModuleId::of::<T>())?;
+        let guard = WriteGuard::try_new(&*entry.module, entry.id.name(), |module| module)
+            .ok_or(Error::WouldBlock(entry.id.name()))?
+            .try_map(|module| (module as &mut dyn Any).downcast_mut())
+            .ok_or(Error::TypeMismatch(type_name::<T>()))?;
+        entry
+            .changed
+            .store(self.change_ticks().this_run, Ordering::Relaxed);
+        Ok(guard)
+    }
+
+    /// Whether `module` has been accessed through [`App::get_module_mut`] or similar since the
+    /// calling module last ran. Outside of a module, whether it ever has been.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if `module` is not registered
+    pub fn is_module_changed(&self, module: ModuleId) -> Result<bool, Error> {
+        let changed = self.module_entry(module)?.changed.load(Ordering::Relaxed);
+        Ok(self.change_ticks().is_changed(changed))
+    }
+
+    /// Hands out a new change tick
+    fn next_tick(&self) -> u64 {
+        self.change_tick.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// The change ticks for a new run of the module whose last run is recorded in `last_run`
+    fn run_ticks(&self, last_run: &AtomicU64) -> Ticks {
+        let this_run = self.next_tick();
+        Ticks {
+            last_run: last_run.swap(this_run, Ordering::Relaxed),
+            this_run,
+        }
+    }
+
+    /// The change ticks for an init or shutdown hook, which don't count as a run of the module
+    fn hook_ticks(&self, last_run: &AtomicU64) -> Ticks {
+        Ticks {
+            last_run: last_run.load(Ordering::Relaxed),
+            this_run: self.next_tick(),
+        }
+    }
+
+    /// The change ticks of the module running on this thread. Code outside of any module gets
+    /// a new tick for each access and treats everything as changed.
+    fn change_ticks(&self) -> Ticks {
+        lock::ticks().unwrap_or_else(|| Ticks {
+            last_run: 0,
+            this_run: self.next_tick(),
         })
     }
 
+    /// Creates a channel for events of type `E`, doing nothing if it already exists
+    pub fn add_event<E: 'static + Send + Sync>(&mut self) {
+        self.events
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Channel::<E>::default()));
+    }
+
+    fn channel<E: 'static + Send + Sync>(&self) -> Result<&Channel<E>, Error> {
+        let channel = self
+            .events
+            .get(&TypeId::of::<E>())
+            .ok_or(Error::MissingEvent(type_name::<E>()))?;
+        (&**channel as &dyn Any)
+            .downcast_ref()
+            .ok_or(Error::TypeMismatch(type_name::<E>()))
+    }
+
+    /// Sends an event to every reader of `E`
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEvent`] if [`App::add_event`] was never called for `E`
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock, such as when the caller is
+    /// still holding [`App::events`] for `E`
+    pub fn send<E: 'static + Send + Sync>(&self, event: E) -> Result<(), Error> {
+        let channel = self.channel::<E>()?;
+        WriteGuard::new(&channel.buffers, type_name::<E>(), |buffers| buffers).send(event);
+        Ok(())
+    }
+
+    /// Every `E` sent since the calling module last read them. Each module, and code outside
+    /// of any module, reads events independently.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingEvent`] if [`App::add_event`] was never called for `E`
+    ///
+    /// # Panics
+    /// Panics in debug builds if taking the lock would deadlock
+    pub fn events<E: 'static + Send + Sync>(&self) -> Result<Events<'_, E>, Error> {
+        let channel = self.channel::<E>()?;
+        let buffers = ReadGuard::new(&channel.buffers, type_name::<E>(), |buffers| buffers);
+        let seen = channel.read(&buffers);
+        Ok(Events { buffers, seen })
+    }
+
+    /// Moves the [`State<S>`] module to `next` straight away, running the exit hooks of the
+    /// current state and then the enter hooks of `next`. Nothing happens if it is already in
+    /// `next`.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingModule`] if no `State<S>` is registered
+    pub fn set_state<S: States>(&mut self, next: S) -> Result<(), Error> {
+        let Some((exit, enter)) = self.get_module::<State<S>>()?.transition(&next) else {
+            return Ok(());
+        };
+        for hook in exit {
+            hook(self);
+        }
+        self.get_module_mut::<State<S>>()?.set(next);
+        for hook in enter {
+            hook(self);
+        }
+        Ok(())
+    }
+
+    /// Runs `future` on the background task pool, delivering its result at the start of a
+    /// stage, either a [`Stage`] or a [`Delivery`] which also names a tick, once it finishes.
+    /// Until then, [`Task::poll`] returns `None`, so modules in the stage and later see the
+    /// result from the same point in a tick. If the work panics, the panic is delivered in
+    /// place of the result as [`Error::TaskPanicked`].
+    ///
+    /// # Panics
+    /// Panics if the task pool's threads cannot be spawned
+    pub fn spawn_task<T: Send + 'static>(
+        &self,
+        at: impl Into<Delivery>,
+        future: impl Future<Output = T> + Send + 'static,
+    ) -> Task<T> {
+        let shared = Arc::new(Shared::new());
+        let finished = shared.clone();
+        self.task_pool
+            .get_or_init(TaskPool::default)
+            .spawn(async move { finished.finish(CatchUnwind::new(future).await) });
+        self.tasks.lock().push((at.into(), shared.clone()));
+        Task { shared }
+    }
+
+    /// Like [`App::spawn_task`], but for blocking work such as loading a file
+    ///
     /// # Panics
-    /// Panics if modules map has a mismatch between the type stated in the key and the type
-    /// stated in the value
+    /// Panics if the task pool's threads cannot be spawned
+    pub fn compute<T: Send + 'static>(
+        &self,
+        at: impl Into<Delivery>,
+        work: impl FnOnce() -> T + Send + 'static,
+    ) -> Task<T> {
+        self.spawn_task(at, async move { work() })
+    }
+
+    /// Replaces the background task pool with one of `threads` threads, which new tasks are
+    /// spawned on. This does not wait for the old pool: tasks already spawned keep running on
+    /// its threads, which exit once they have finished. By default there is one thread per
+    /// CPU.
+    ///
+    /// # Panics
+    /// Panics if `threads` is zero or a thread cannot be spawned
+    pub fn set_task_threads(&mut self, threads: usize) {
+        self.task_pool = TaskPool::new(threads).into();
+    }
+
+    /// The order in which modules will run on the next tick. Its [`Display`] lists each
+    /// stage's batches, for logging or debugging.
     #[must_use]
-    pub fn get_module<T: 'static>(&self) -> Option<MappedRwLockReadGuard<'_, RawRwLock, T>> {
-        self.modules.get(&TypeId::of::<T>()).map(|module| {
-            RwLockReadGuard::map(module.read(), |module| {
-                (module as &dyn Any).downcast_ref().unwrap()
-            })
-        })
+    pub const fn execution_plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// Queues structural changes, such as spawning entities or registering modules, from
+    /// places which only have `&App`
+    #[must_use]
+    pub const fn commands(&self) -> Commands<'_> {
+        Commands { app: self }
     }
 
+    /// Applies every queued command in the order it was recorded, including any queued by the
+    /// commands themselves
+    pub fn apply_commands(&mut self) {
+        loop {
+            let commands = std::mem::take(self.commands.get_mut());
+            if commands.is_empty() {
+                break;
+            }
+            for command in commands {
+                command(self);
+            }
+        }
+    }
+
+    /// Initialises any modules which have not yet been, in the order they were registered
+    ///
     /// # Panics
-    /// Panics if modules map has a mismatch between the type stated in the key and the type
-    /// stated in the value
+    /// Panics if a module registered while initialising has an invalid schedule
+    fn init(&mut self) {
+        let mut initialized = false;
+        while let Some(id) = self
+            .modules
+            .iter()
+            .filter(|(_, entry)| !entry.initialized)
+            .min_by_key(|(_, entry)| entry.order)
+            .map(|(id, _)| *id)
+        {
+            let Some(mut entry) = self.modules.remove(&id) else {
+                break;
+            };
+            self.initializing = Some(id);
+            {
+                let _running = lock::running(entry.id, self.hook_ticks(&entry.last_run));
+                entry.module.get_mut().init(self);
+            }
+            self.initializing = None;
+            entry.initialized = true;
+            self.modules.insert(id, entry);
+            initialized = true;
+        }
+
+        // Modules registered by an init hook were scheduled without the module running it
+        if initialized {
+            self.plan = self
+                .build_plan()
+                .unwrap_or_else(|err| panic!("failed to schedule initialised modules: {err}"));
+        }
+    }
+
+    /// Runs the shutdown hook of every initialised module, in the reverse of the order they
+    /// tick in. Every hook runs even if an earlier one panics, the first panic is resumed
+    /// afterwards.
+    ///
+    /// Modules will be initialised again before the next tick.
+    pub fn shutdown(&mut self) {
+        let app: &Self = self;
+        let mut payload = None;
+        let order = app
+            .plan
+            .stages
+            .iter()
+            .flat_map(|(_, batches)| batches.iter().flatten())
+            .rev();
+        for id in order {
+            let entry = &app.modules[id];
+            if !entry.initialized {
+                continue;
+            }
+            let _running = lock::running(entry.id, app.hook_ticks(&entry.last_run));
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| entry.module.write().shutdown(app)));
+            if let Err(err) = result {
+                payload.get_or_insert(err);
+            }
+        }
+
+        for entry in self.modules.values_mut() {
+            entry.initialized = false;
+        }
+        if let Some(payload) = payload {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// The fixed timestep, and how far through it the current tick is
     #[must_use]
-    pub fn get_module_mut<T: 'static>(&self) -> Option<MappedRwLockWriteGuard<'_, RawRwLock, T>> {
-        self.modules.get(&TypeId::of::<T>()).map(|module| {
-            RwLockWriteGuard::map(module.write(), |module| {
-                (module as &mut dyn Any).downcast_mut().unwrap()
-            })
-        })
+    pub const fn fixed_time(&self) -> &FixedTime {
+        &self.fixed_time
     }
 
-    pub fn tick(&self) {
-        self.modules.par_iter().for_each(|(_, module)| {
-            module.write().tick(self);
-        });
+    pub const fn fixed_time_mut(&mut self) -> &mut FixedTime {
+        &mut self.fixed_time
+    }
+
+    /// How many ticks have finished
+    #[must_use]
+    pub const fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The total time every finished tick has advanced by
+    #[must_use]
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// How much time the current tick advances by, or the last one outside of a tick
+    #[must_use]
+    pub const fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// When enabled, modules run one at a time on the calling thread, in the order of the
+    /// execution plan, rather than in parallel. Along with [`RunMode::Fixed`] this makes runs
+    /// reproducible, as commands and events are then always recorded in the same order.
+    pub const fn set_stepping(&mut self, stepping: bool) {
+        self.stepping = stepping;
+    }
+
+    /// Runs every module once, stage by stage, except for modules in [`Stage::FixedUpdate`]
+    /// which run once for every fixed timestep which has passed since the last tick. Within a
+    /// stage, modules without ordering constraints or conflicting access between them run in
+    /// parallel.
+    ///
+    /// Once every module has run, the commands they queued are applied and events sent during
+    /// the previous tick are dropped.
+    ///
+    /// Modules which have not yet been initialised are initialised first.
+    ///
+    /// # Panics
+    /// Panics if a module registered while initialising modules, or by a command, has an
+    /// invalid schedule
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let delta = self.last_tick.map_or(Duration::ZERO, |last| now - last);
+        self.last_tick = Some(now);
+        self.advance(delta);
+    }
+
+    /// Like [`App::tick`], but as if `delta` had passed since the last tick rather than
+    /// measuring it
+    ///
+    /// # Panics
+    /// Panics if a module registered while initialising modules, or by a command, has an
+    /// invalid schedule
+    pub fn advance(&mut self, delta: Duration) {
+        self.init();
+        self.delta = delta;
+        let start = Instant::now();
+        self.profiling = self.modules.contains_key(&ModuleId::of::<Profiler>());
+        let steps = self.fixed_time.accumulate(delta);
+        for stage in Stage::ALL {
+            let tick = self.ticks;
+            self.tasks
+                .get_mut()
+                .retain(|(at, task)| !at.is_due(tick, stage) || !task.deliver());
+            let Some((_, batches)) = self.plan.stages.iter().find(|(at, _)| *at == stage) else {
+                continue;
+            };
+            let repeat = if stage == Stage::FixedUpdate {
+                steps
+            } else {
+                1
+            };
+            for _ in 0..repeat {
+                self.run_batches(batches);
+            }
+        }
+        self.apply_commands();
+        self.notify_changes();
+        for channel in self.events.values_mut() {
+            channel.update();
+        }
+        self.ticks += 1;
+        self.elapsed += delta;
+        if self.profiling {
+            let frame = Frame {
+                start,
+                duration: start.elapsed(),
+                samples: mem::take(self.samples.get_mut()),
+            };
+            if let Ok(mut profiler) = self.get_module_mut::<Profiler>() {
+                profiler.record(frame);
+            }
+        }
+    }
+
+    fn run_batches(&self, batches: &[Vec<ModuleId>]) {
+        for batch in batches {
+            if self.stepping {
+                for id in batch {
+                    self.run_module(id);
+                }
+            } else {
+                batch.par_iter().for_each(|id| self.run_module(id));
+            }
+        }
+    }
+
+    fn run_module(&self, id: &ModuleId) {
+        let entry = &self.modules[id];
+        if !entry.enabled {
+            return;
+        }
+        // Checked first so that a skipped run keeps its last tick, and with it the changes made
+        // since the module last ticked
+        let should_run = {
+            let _checking = lock::checking(entry.id, self.hook_ticks(&entry.last_run));
+            entry.schedule.should_run(self)
+        };
+        if !should_run {
+            return;
+        }
+        let running = lock::running(entry.id, self.run_ticks(&entry.last_run));
+        let start = self.profiling.then(Instant::now);
+        let mut module = WriteGuard::new(&*entry.module, entry.id.name(), |module| module);
+        module.tick(self);
+        drop(module);
+        if let Some(start) = start {
+            self.samples.lock().push(Sample {
+                module: entry.id,
+                start,
+                duration: start.elapsed(),
+                lock_wait: running.lock_wait(),
+                thread: rayon::current_thread_index(),
+            });
+        }
     }
 
     pub fn exit(&self) {
         *self.running.write() = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{panic, sync::atomic::AtomicBool};
+
+    static HOOKS: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+    #[derive(Debug)]
+    struct First;
+
+    impl Module for First {
+        fn init(&mut self, app: &mut App) {
+            HOOKS.lock().push("init first");
+            app.register_module(Second).unwrap();
+        }
+
+        fn shutdown(&mut self, _: &App) {
+            HOOKS.lock().push("shutdown first");
+        }
+    }
+
+    #[derive(Debug)]
+    struct Second;
+
+    impl Module for Second {
+        fn init(&mut self, _: &mut App) {
+            HOOKS.lock().push("init second");
+        }
+
+        fn tick(&mut self, _: &App) {
+            panic!("second panicked");
+        }
+
+        fn shutdown(&mut self, _: &App) {
+            HOOKS.lock().push("shutdown second");
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().after::<First>()
+        }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut app = App::new();
+        app.register_module(First).unwrap();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| app.run()));
+        assert!(result.is_err());
+        assert_eq!(
+            *HOOKS.lock(),
+            [
+                "init first",
+                "init second",
+                "shutdown second",
+                "shutdown first"
+            ]
+        );
+    }
+
+    /// Records whether registering another of itself while initialising was rejected
+    #[derive(Debug)]
+    struct Twin(bool);
+
+    impl Module for Twin {
+        fn init(&mut self, app: &mut App) {
+            self.0 = matches!(
+                app.register_module(Self(false)),
+                Err(Error::Schedule(ScheduleError::Duplicate(_)))
+            );
+        }
+    }
+
+    static MAIN_THREAD_TWIN: AtomicBool = AtomicBool::new(false);
+
+    impl MainThreadModule for Twin {
+        fn init(&mut self, app: &mut MainThreadApp) {
+            let rejected = matches!(
+                app.register_main_thread_module(Self(false)),
+                Err(Error::Schedule(ScheduleError::Duplicate(_)))
+            );
+            MAIN_THREAD_TWIN.store(rejected, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_init_duplicate() {
+        let mut app = App::new();
+        app.register_module(Twin(false)).unwrap();
+        app.register_main_thread_module(Twin(false)).unwrap();
+        app.tick();
+        assert!(app.get_module::<Twin>().unwrap().0);
+        assert!(MAIN_THREAD_TWIN.load(Ordering::Relaxed));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Gravity(i32);
+
+    impl Module for Gravity {
+        fn tick(&mut self, _: &App) {
+            self.0 -= 1;
+        }
+    }
+
+    #[derive(Debug)]
+    struct Screen;
+
+    impl MainThreadModule for Screen {}
+
+    #[test]
+    fn test_labelled_modules() {
+        let mut app = App::new();
+        app.register_module_as("zone_a", Gravity(10)).unwrap();
+        app.register_module_as("zone_b", Gravity(20)).unwrap();
+        app.register_module(Gravity(30)).unwrap();
+        assert!(matches!(
+            app.register_module_as("zone_a", Gravity(0)),
+            Err(Error::Schedule(ScheduleError::Duplicate(id)))
+                if id == ModuleId::labelled::<Gravity>("zone_a")
+        ));
+        app.register_main_thread_module(Screen).unwrap();
+        assert!(matches!(
+            app.register_main_thread_module(Screen),
+            Err(Error::Schedule(ScheduleError::Duplicate(id))) if id == ModuleId::of::<Screen>()
+        ));
+        app.tick();
+
+        assert_eq!(
+            *app.get_module_as::<Gravity>("zone_b").unwrap(),
+            Gravity(19)
+        );
+        assert_eq!(*app.get_module::<Gravity>().unwrap(), Gravity(29));
+        assert_eq!(
+            app.modules_of::<Gravity>()
+                .map(|instance| instance.map(|(label, gravity)| (label, gravity.0)))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            [(Some("zone_a"), 9), (Some("zone_b"), 19), (None, 29)]
+        );
+
+        assert_eq!(app.remove_module_as::<Gravity>("zone_a").unwrap().0, 9);
+        assert_eq!(app.modules_of::<Gravity>().count(), 2);
+    }
+}
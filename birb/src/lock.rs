@@ -0,0 +1,501 @@
+use parking_lot::{
+    lock_api::{MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLockReadGuard, RwLockWriteGuard},
+    RawRwLock, RwLock,
+};
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug},
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use crate::{component::Ticks, ModuleId};
+
+/// Shared access to a module or to entities, released when dropped
+pub struct ReadGuard<'a, T: ?Sized> {
+    guard: MappedRwLockReadGuard<'a, RawRwLock, T>,
+    _held: Held,
+}
+
+impl<'a, T: ?Sized> ReadGuard<'a, T> {
+    pub(crate) fn new<U: ?Sized>(
+        lock: &'a RwLock<U>,
+        name: &'static str,
+        map: impl FnOnce(&U) -> &T,
+    ) -> Self {
+        let (guard, held) = lock.try_read().map_or_else(
+            || {
+                let pending = Held::acquire(lock, name, Mode::Read);
+                (blocking(|| lock.read()), pending.acquired())
+            },
+            |guard| (guard, Held::new(lock, name, Mode::Read)),
+        );
+        Self {
+            guard: RwLockReadGuard::map(guard, map),
+            _held: held,
+        }
+    }
+
+    pub(crate) fn try_new<U: ?Sized>(
+        lock: &'a RwLock<U>,
+        name: &'static str,
+        map: impl FnOnce(&U) -> &T,
+    ) -> Option<Self> {
+        let guard = lock.try_read()?;
+        Some(Self {
+            guard: RwLockReadGuard::map(guard, map),
+            _held: Held::new(lock, name, Mode::Read),
+        })
+    }
+
+    /// Narrows the guard to part of the locked value, releasing the lock if `map` fails
+    pub(crate) fn try_map<U: ?Sized>(
+        self,
+        map: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<ReadGuard<'a, U>> {
+        let Self { guard, _held: held } = self;
+        let guard = MappedRwLockReadGuard::try_map(guard, map).ok()?;
+        Some(ReadGuard { guard, _held: held })
+    }
+}
+
+impl<T: ?Sized> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for ReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.guard.fmt(f)
+    }
+}
+
+/// Exclusive access to a module or to entities, released when dropped
+pub struct WriteGuard<'a, T: ?Sized> {
+    guard: MappedRwLockWriteGuard<'a, RawRwLock, T>,
+    _held: Held,
+}
+
+impl<'a, T: ?Sized> WriteGuard<'a, T> {
+    pub(crate) fn new<U: ?Sized>(
+        lock: &'a RwLock<U>,
+        name: &'static str,
+        map: impl FnOnce(&mut U) -> &mut T,
+    ) -> Self {
+        let (guard, held) = lock.try_write().map_or_else(
+            || {
+                let pending = Held::acquire(lock, name, Mode::Write);
+                (blocking(|| lock.write()), pending.acquired())
+            },
+            |guard| (guard, Held::new(lock, name, Mode::Write)),
+        );
+        Self {
+            guard: RwLockWriteGuard::map(guard, map),
+            _held: held,
+        }
+    }
+
+    pub(crate) fn try_new<U: ?Sized>(
+        lock: &'a RwLock<U>,
+        name: &'static str,
+        map: impl FnOnce(&mut U) -> &mut T,
+    ) -> Option<Self> {
+        let guard = lock.try_write()?;
+        Some(Self {
+            guard: RwLockWriteGuard::map(guard, map),
+            _held: Held::new(lock, name, Mode::Write),
+        })
+    }
+
+    /// Narrows the guard to part of the locked value, releasing the lock if `map` fails
+    pub(crate) fn try_map<U: ?Sized>(
+        self,
+        map: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<WriteGuard<'a, U>> {
+        let Self { guard, _held: held } = self;
+        let guard = MappedRwLockWriteGuard::try_map(guard, map).ok()?;
+        Some(WriteGuard { guard, _held: held })
+    }
+}
+
+impl<T: ?Sized> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for WriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.guard.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Read,
+    Write,
+}
+
+/// Records, in debug builds, that the current thread holds a lock until dropped
+struct Held {
+    #[cfg(debug_assertions)]
+    lock: usize,
+    #[cfg(debug_assertions)]
+    mode: Mode,
+}
+
+/// A lock which the current thread is about to block on
+struct Pending {
+    #[cfg(debug_assertions)]
+    lock: usize,
+    #[cfg(debug_assertions)]
+    name: &'static str,
+    #[cfg(debug_assertions)]
+    mode: Mode,
+}
+
+/// Marks the current thread as ticking a module until dropped
+pub(crate) struct Running;
+
+#[cfg(debug_assertions)]
+fn address<T: ?Sized>(lock: &RwLock<T>) -> usize {
+    std::ptr::from_ref(lock).cast::<()>() as usize
+}
+
+#[cfg(debug_assertions)]
+impl Held {
+    /// Checks that blocking on `lock` cannot deadlock, panicking if it would
+    fn acquire<T: ?Sized>(lock: &RwLock<T>, name: &'static str, mode: Mode) -> Pending {
+        let lock = address(lock);
+        tracking::wait(lock, name, mode);
+        Pending { lock, name, mode }
+    }
+
+    /// Records a lock which was taken without blocking
+    fn new<T: ?Sized>(lock: &RwLock<T>, name: &'static str, mode: Mode) -> Self {
+        let lock = address(lock);
+        tracking::hold(lock, name, mode);
+        Self { lock, mode }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Pending {
+    fn acquired(self) -> Held {
+        tracking::hold(self.lock, self.name, self.mode);
+        Held {
+            lock: self.lock,
+            mode: self.mode,
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for Held {
+    fn drop(&mut self) {
+        tracking::release(self.lock, self.mode);
+    }
+}
+
+impl Running {
+    /// How long the module has spent blocked on locks so far
+    #[allow(clippy::unused_self)]
+    pub fn lock_wait(&self) -> Duration {
+        RUNNING.with_borrow(|running| {
+            running
+                .last()
+                .map_or(Duration::ZERO, |module| module.lock_wait)
+        })
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.with_borrow_mut(Vec::pop);
+    }
+}
+
+struct RunningModule {
+    id: ModuleId,
+    ticks: Ticks,
+    /// The time the module has spent blocked on locks
+    lock_wait: Duration,
+    /// Whether the module's run conditions are being checked rather than the module ticked
+    checking: bool,
+}
+
+thread_local! {
+    /// The modules currently ticking on this thread, innermost last. There can be more than
+    /// one when rayon steals a module's tick while another is waiting on a parallel iterator.
+    static RUNNING: RefCell<Vec<RunningModule>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Takes a lock which is already known to be contended, charging the time spent blocked to the
+/// module running on this thread
+fn blocking<G>(lock: impl FnOnce() -> G) -> G {
+    let start = Instant::now();
+    let guard = lock();
+    let waited = start.elapsed();
+    RUNNING.with_borrow_mut(|running| {
+        if let Some(module) = running.last_mut() {
+            module.lock_wait += waited;
+        }
+    });
+    guard
+}
+
+/// Marks the current thread as running `module` until the returned value is dropped, so that
+/// deadlock reports can say which module took which lock, event readers can tell modules apart
+/// and changes are marked with the module's `ticks`
+pub(crate) fn running(module: ModuleId, ticks: Ticks) -> Running {
+    push(module, ticks, false)
+}
+
+/// Like [`running`], but while checking the module's run conditions, so that events they read
+/// are left for the module's tick
+pub(crate) fn checking(module: ModuleId, ticks: Ticks) -> Running {
+    push(module, ticks, true)
+}
+
+fn push(module: ModuleId, ticks: Ticks, checking: bool) -> Running {
+    RUNNING.with_borrow_mut(|running| {
+        running.push(RunningModule {
+            id: module,
+            ticks,
+            lock_wait: Duration::ZERO,
+            checking,
+        });
+    });
+    Running
+}
+
+/// The module running on this thread, if any
+pub(crate) fn current() -> Option<ModuleId> {
+    RUNNING.with_borrow(|running| running.last().map(|module| module.id))
+}
+
+/// Whether the run conditions of the module on this thread are being checked
+pub(crate) fn is_checking() -> bool {
+    RUNNING.with_borrow(|running| running.last().is_some_and(|module| module.checking))
+}
+
+/// The change ticks of the module running on this thread, if any
+pub(crate) fn ticks() -> Option<Ticks> {
+    RUNNING.with_borrow(|running| running.last().map(|module| module.ticks))
+}
+
+#[cfg(not(debug_assertions))]
+#[allow(clippy::unused_self, clippy::missing_const_for_fn)]
+impl Held {
+    fn acquire<T: ?Sized>(_: &RwLock<T>, _: &'static str, _: Mode) -> Pending {
+        Pending {}
+    }
+
+    fn new<T: ?Sized>(_: &RwLock<T>, _: &'static str, _: Mode) -> Self {
+        Self {}
+    }
+}
+
+#[cfg(not(debug_assertions))]
+#[allow(clippy::unused_self, clippy::missing_const_for_fn)]
+impl Pending {
+    fn acquired(self) -> Held {
+        Held {}
+    }
+}
+
+#[cfg(debug_assertions)]
+mod tracking {
+    use super::{current, Mode};
+    use crate::ModuleId;
+    use parking_lot::Mutex;
+    use std::{
+        collections::{HashMap, HashSet},
+        fmt::Write,
+        sync::LazyLock,
+        thread::{self, ThreadId},
+    };
+
+    struct Holder {
+        thread: ThreadId,
+        mode: Mode,
+        module: Option<ModuleId>,
+    }
+
+    #[derive(Default)]
+    struct Locks {
+        names: HashMap<usize, &'static str>,
+        holders: HashMap<usize, Vec<Holder>>,
+        waiting: HashMap<ThreadId, (usize, Mode)>,
+    }
+
+    /// A module waiting for a lock, and the module holding it
+    type Link = (Option<ModuleId>, usize, Option<ModuleId>);
+
+    static LOCKS: LazyLock<Mutex<Locks>> = LazyLock::new(Mutex::default);
+
+    fn describe(module: Option<ModuleId>) -> String {
+        module.map_or_else(
+            || String::from("code outside of any module"),
+            |module| format!("module `{module}`"),
+        )
+    }
+
+    pub fn wait(lock: usize, name: &'static str, mode: Mode) {
+        let thread = thread::current().id();
+        let mut locks = LOCKS.lock();
+        locks.names.insert(lock, name);
+
+        let conflicting = locks.holders.get(&lock).and_then(|holders| {
+            holders.iter().find(|holder| {
+                holder.thread == thread && (holder.mode == Mode::Write || mode == Mode::Write)
+            })
+        });
+        if let Some(holder) = conflicting {
+            let message = format!(
+                "deadlock: {} tried to lock `{name}` for {} on a thread where {} already holds it \
+                 for {}",
+                describe(current()),
+                mode.as_str(),
+                describe(holder.module),
+                holder.mode.as_str(),
+            );
+            drop(locks);
+            panic!("{message}");
+        }
+
+        locks.waiting.insert(thread, (lock, mode));
+        if let Some(cycle) = locks.cycle(thread, lock, mode) {
+            locks.waiting.remove(&thread);
+            let mut message = String::from("deadlock:");
+            for (waiter, lock, holder) in cycle {
+                let _ = write!(
+                    message,
+                    " {} is waiting for `{}`, held by {};",
+                    describe(waiter),
+                    locks.names[&lock],
+                    describe(holder),
+                );
+            }
+            message.pop();
+            drop(locks);
+            panic!("{message}");
+        }
+    }
+
+    pub fn hold(lock: usize, name: &'static str, mode: Mode) {
+        let thread = thread::current().id();
+        let mut locks = LOCKS.lock();
+        locks.names.insert(lock, name);
+        locks.waiting.remove(&thread);
+        locks.holders.entry(lock).or_default().push(Holder {
+            thread,
+            mode,
+            module: current(),
+        });
+    }
+
+    pub fn release(lock: usize, mode: Mode) {
+        let thread = thread::current().id();
+        let mut locks = LOCKS.lock();
+        if let Some(holders) = locks.holders.get_mut(&lock) {
+            if let Some(index) = holders
+                .iter()
+                .position(|holder| holder.thread == thread && holder.mode == mode)
+            {
+                holders.swap_remove(index);
+            }
+            if holders.is_empty() {
+                locks.holders.remove(&lock);
+                locks.names.remove(&lock);
+            }
+        }
+    }
+
+    impl Locks {
+        /// Follows the chain of "waiting for a lock held by a thread waiting for ..." starting
+        /// from `thread`, returning each link if it leads back to `thread`
+        fn cycle(&self, thread: ThreadId, lock: usize, mode: Mode) -> Option<Vec<Link>> {
+            let mut visited = HashSet::new();
+            self.search(thread, current(), (lock, mode), &mut visited)
+        }
+
+        fn search(
+            &self,
+            origin: ThreadId,
+            waiter: Option<ModuleId>,
+            (lock, mode): (usize, Mode),
+            visited: &mut HashSet<usize>,
+        ) -> Option<Vec<Link>> {
+            if !visited.insert(lock) {
+                return None;
+            }
+            // Readers only block on writers
+            let blocking = self
+                .holders
+                .get(&lock)?
+                .iter()
+                .filter(|holder| holder.mode == Mode::Write || mode == Mode::Write);
+            for holder in blocking {
+                if holder.thread == origin {
+                    return Some(vec![(waiter, lock, holder.module)]);
+                }
+                let Some(next) = self.waiting.get(&holder.thread) else {
+                    continue;
+                };
+                if let Some(mut rest) = self.search(origin, holder.module, *next, visited) {
+                    rest.insert(0, (waiter, lock, holder.module));
+                    return Some(rest);
+                }
+            }
+            None
+        }
+    }
+
+    impl Mode {
+        const fn as_str(self) -> &'static str {
+            match self {
+                Self::Read => "reading",
+                Self::Write => "writing",
+            }
+        }
+    }
+}
+
+// Deadlocks are only detected in debug builds
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use crate::{App, Error, Module};
+
+    #[derive(Debug)]
+    struct Reentrant;
+
+    impl Module for Reentrant {
+        fn tick(&mut self, app: &App) {
+            assert!(matches!(
+                app.try_get_module_mut::<Self>(),
+                Err(Error::WouldBlock(_))
+            ));
+            let _ = app.get_module_mut::<Self>();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "deadlock: module `birb::lock::tests::Reentrant` tried to lock")]
+    fn test_reentrant() {
+        let mut app = App::new();
+        app.register_module(Reentrant).unwrap();
+        app.tick();
+    }
+}
@@ -0,0 +1,243 @@
+use std::{any::TypeId, collections::HashMap, sync::Arc};
+
+use crate::{component::Component, entity::Entity, App};
+
+pub(crate) type Observer = Arc<dyn Fn(&App, Entity) + Send + Sync>;
+
+/// The changes observers can be registered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Trigger {
+    Add,
+    Change,
+    Remove,
+}
+
+/// Observers registered on an [`App`], keyed by component and trigger
+#[derive(Default)]
+pub(crate) struct Observers {
+    observers: HashMap<(TypeId, Trigger), Vec<Observer>>,
+    /// The change tick up to which changes have been reported to change observers
+    reported: u64,
+}
+
+impl Observers {
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    fn add(&mut self, component: TypeId, trigger: Trigger, observer: Observer) {
+        self.observers
+            .entry((component, trigger))
+            .or_default()
+            .push(observer);
+    }
+
+    /// The components which have change observers
+    fn observed_changes(&self) -> Vec<TypeId> {
+        self.observers
+            .keys()
+            .filter(|(_, trigger)| *trigger == Trigger::Change)
+            .map(|(component, _)| *component)
+            .collect()
+    }
+
+    /// The observers of `trigger` on any of `components`, in registration order for each
+    fn matching(
+        &self,
+        trigger: Trigger,
+        components: impl IntoIterator<Item = TypeId>,
+    ) -> Vec<Observer> {
+        components
+            .into_iter()
+            .filter_map(|component| self.observers.get(&(component, trigger)))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+impl App {
+    /// Runs `observer` whenever a `T` is added to an entity, by spawning it or by inserting a
+    /// `T` into an entity which did not have one
+    pub fn on_add<T: Component>(
+        &mut self,
+        observer: impl Fn(&Self, Entity) + Send + Sync + 'static,
+    ) {
+        self.observers
+            .add(TypeId::of::<T>(), Trigger::Add, Arc::new(observer));
+    }
+
+    /// Runs `observer` between ticks, once commands are applied, for every entity whose `T` has
+    /// been mutably accessed since change observers last ran, whether through
+    /// [`App::get_mut`], [`App::get_entity_mut`], a query or [`App::insert`]. As with
+    /// [`Changed`](crate::query::Changed), this includes components added since then, and a
+    /// mutable query counts as accessing every entity it matched. Changes made by change
+    /// observers themselves are not reported, so observers which update each other's
+    /// components don't set each other off every tick.
+    pub fn on_change<T: Component>(
+        &mut self,
+        observer: impl Fn(&Self, Entity) + Send + Sync + 'static,
+    ) {
+        self.observers
+            .add(TypeId::of::<T>(), Trigger::Change, Arc::new(observer));
+    }
+
+    /// Runs `observer` whenever a `T` is removed from an entity, or the entity is despawned.
+    /// It runs beforehand, so the component can still be read.
+    pub fn on_remove<T: Component>(
+        &mut self,
+        observer: impl Fn(&Self, Entity) + Send + Sync + 'static,
+    ) {
+        self.observers
+            .add(TypeId::of::<T>(), Trigger::Remove, Arc::new(observer));
+    }
+
+    /// Runs the change observers for every entity whose component has been mutably accessed
+    /// since they last ran
+    pub(crate) fn notify_changes(&mut self) {
+        let observed = self.observers.observed_changes();
+        if observed.is_empty() {
+            return;
+        }
+        // Every change is found before any observer runs, so that the observers' own writes
+        // are never reported, neither now nor next time
+        let since = self.observers.reported;
+        let changed: Vec<(TypeId, Entity)> = observed
+            .into_iter()
+            .flat_map(|component| {
+                self.archetypes
+                    .containing(component)
+                    .into_iter()
+                    .flatten()
+                    .flat_map(move |archetype| {
+                        let column = archetype.column(component);
+                        archetype
+                            .entities
+                            .iter()
+                            .enumerate()
+                            .filter(move |&(row, _)| {
+                                column.is_some_and(|column| column.changed_at(row) > since)
+                            })
+                            .map(move |(_, entity)| (component, *entity))
+                    })
+            })
+            .collect();
+        for (component, entity) in changed {
+            self.notify(Trigger::Change, entity, [component]);
+        }
+        self.observers.reported = self.next_tick();
+    }
+
+    /// Runs the observers of `trigger` on any of `components` for `entity`
+    pub(crate) fn notify(
+        &self,
+        trigger: Trigger,
+        entity: Entity,
+        components: impl IntoIterator<Item = TypeId>,
+    ) {
+        if self.observers.is_empty() {
+            return;
+        }
+        for observer in self.observers.matching(trigger, components) {
+            observer(self, entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use crate::{entity::Entity, App, Module};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Name(&'static str);
+
+    /// Looks entities up by their [`Name`]
+    #[derive(Debug, Default)]
+    struct Names(HashMap<&'static str, Entity>);
+
+    impl Module for Names {}
+
+    #[test]
+    fn test_observers() {
+        let mut app = App::new();
+        app.register_module(Names::default()).unwrap();
+        app.on_add::<Name>(|app, entity| {
+            let name = app.get::<Name>(entity).unwrap().0;
+            app.get_module_mut::<Names>()
+                .unwrap()
+                .0
+                .insert(name, entity);
+        });
+        app.on_change::<Name>(|app, entity| {
+            let name = app.get::<Name>(entity).unwrap().0;
+            let mut names = app.get_module_mut::<Names>().unwrap();
+            names.0.retain(|_, named| *named != entity);
+            names.0.insert(name, entity);
+        });
+        app.on_remove::<Name>(|app, entity| {
+            let name = app.get::<Name>(entity).unwrap().0;
+            app.get_module_mut::<Names>().unwrap().0.remove(name);
+        });
+
+        let tank = app.spawn((Name("tank"), 0u32));
+        let crate_ = app.commands().spawn((Name("crate"),));
+        let unnamed = app.spawn((1u32,));
+        app.apply_commands();
+        app.insert(unnamed, (Name("jeep"),)).unwrap();
+        app.insert(tank, (Name("truck"),)).unwrap();
+        app.despawn(crate_).unwrap();
+        app.remove::<Name>(unnamed).unwrap();
+        app.tick();
+        let names = app.get_module::<Names>().unwrap().0.clone();
+        assert_eq!(names, HashMap::from([("truck", tank)]));
+
+        *app.get_mut::<Name>(tank).unwrap() = Name("tanker");
+        app.tick();
+        let names = app.get_module::<Names>().unwrap().0.clone();
+        assert_eq!(names, HashMap::from([("tanker", tank)]));
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct Celsius(f32);
+
+    #[derive(Debug, Clone, Copy)]
+    struct Fahrenheit(f32);
+
+    #[test]
+    fn test_observers_writing() {
+        let mut app = App::new();
+        let notified = Arc::new(AtomicUsize::new(0));
+        let count = notified.clone();
+        app.on_change::<Celsius>(move |app, entity| {
+            count.fetch_add(1, Ordering::Relaxed);
+            let celsius = app.get::<Celsius>(entity).unwrap().0;
+            app.get_mut::<Fahrenheit>(entity).unwrap().0 = celsius.mul_add(1.8, 32.0);
+        });
+        let count = notified.clone();
+        app.on_change::<Fahrenheit>(move |app, entity| {
+            count.fetch_add(1, Ordering::Relaxed);
+            let fahrenheit = app.get::<Fahrenheit>(entity).unwrap().0;
+            app.get_mut::<Celsius>(entity).unwrap().0 = (fahrenheit - 32.0) / 1.8;
+        });
+
+        let thermometer = app.spawn((Celsius(100.0), Fahrenheit(0.0)));
+        app.tick();
+        assert_eq!(notified.load(Ordering::Relaxed), 2);
+        notified.store(0, Ordering::Relaxed);
+        app.tick();
+        assert_eq!(notified.load(Ordering::Relaxed), 0);
+
+        app.get_mut::<Celsius>(thermometer).unwrap().0 = 0.0;
+        app.tick();
+        assert_eq!(notified.load(Ordering::Relaxed), 1);
+        assert!(app.get::<Fahrenheit>(thermometer).unwrap().0 > 31.0);
+    }
+}
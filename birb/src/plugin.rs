@@ -0,0 +1,109 @@
+use std::{
+    any::{type_name, TypeId},
+    fmt::{self, Display},
+};
+
+use crate::{Error, MainThreadApp};
+
+/// Bundles the modules, events and entities a feature needs, added with
+/// [`MainThreadApp::add_plugin`].
+///
+/// Configuration is passed in as fields of the plugin value.
+pub trait Plugin: Sized + 'static {
+    /// Used when reporting errors, the type name by default
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// Plugins which must have been added before this one
+    fn requires(&self) -> Vec<PluginId> {
+        Vec::new()
+    }
+
+    /// Registers everything the plugin provides
+    ///
+    /// # Errors
+    /// Returns an error if anything it registers cannot be
+    fn build(self, app: &mut MainThreadApp) -> Result<(), Error>;
+}
+
+/// Identifies a plugin by its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PluginId {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl PluginId {
+    #[must_use]
+    pub fn of<P: Plugin>() -> Self {
+        Self {
+            id: TypeId::of::<P>(),
+            name: type_name::<P>(),
+        }
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl Display for PluginId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Plugin, PluginId};
+    use crate::{App, Error, MainThreadApp, Module};
+
+    #[derive(Debug)]
+    struct Gravity(i32);
+
+    impl Module for Gravity {}
+
+    struct Physics {
+        gravity: i32,
+    }
+
+    impl Plugin for Physics {
+        fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+            app.register_module(Gravity(self.gravity))
+        }
+    }
+
+    struct Ragdolls;
+
+    impl Plugin for Ragdolls {
+        fn requires(&self) -> Vec<PluginId> {
+            vec![PluginId::of::<Physics>()]
+        }
+
+        fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+            app.spawn((0u32,));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plugins() {
+        let mut app = App::new();
+        assert!(matches!(
+            app.add_plugin(Ragdolls),
+            Err(Error::MissingPlugin { .. })
+        ));
+        assert!(app.get_entity::<u32>().is_err());
+
+        app.add_plugin(Physics { gravity: -10 }).unwrap();
+        app.add_plugin(Ragdolls).unwrap();
+        assert!(app.has_plugin::<Ragdolls>());
+        assert_eq!(app.get_module::<Gravity>().unwrap().0, -10);
+        assert!(matches!(
+            app.add_plugin(Physics { gravity: 0 }),
+            Err(Error::DuplicatePlugin(_))
+        ));
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, Module, ModuleId};
+
+/// One run of one module's tick
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub module: ModuleId,
+    pub start: Instant,
+    /// How long the tick took, including waiting for the module's own lock
+    pub duration: Duration,
+    /// How much of `duration` was spent blocked on the module's own lock and on the module,
+    /// component and event locks it took while ticking. Brief waits on internal queues, such
+    /// as the one commands go on, are not counted.
+    pub lock_wait: Duration,
+    /// The index of the rayon worker the tick ran on, or `None` for the thread calling
+    /// [`App::tick`](crate::App::tick)
+    pub thread: Option<usize>,
+}
+
+/// Every module tick run during one call to [`App::advance`](crate::App::advance)
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub start: Instant,
+    pub duration: Duration,
+    pub samples: Vec<Sample>,
+}
+
+/// Summary of a set of durations
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    /// The duration which 99% of the samples took at most
+    pub p99: Duration,
+}
+
+impl Stats {
+    fn of(mut durations: Vec<Duration>) -> Option<Self> {
+        durations.sort_unstable();
+        let (&min, &max) = (durations.first()?, durations.last()?);
+        let count = durations.len();
+        let total: Duration = durations.iter().sum();
+        Some(Self {
+            count,
+            min,
+            avg: total / u32::try_from(count).unwrap_or(u32::MAX),
+            max,
+            p99: durations[(count * 99).div_ceil(100) - 1],
+        })
+    }
+}
+
+/// How long one module's ticks took over the profiler's window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleStats {
+    pub tick: Stats,
+    /// See [`Sample::lock_wait`]
+    pub lock_wait: Stats,
+}
+
+/// Records how long every module's tick takes while it is registered.
+///
+/// Only the most recent frames are kept, so the statistics are rolling. Modules in
+/// [`Stage::FixedUpdate`](crate::schedule::Stage::FixedUpdate) contribute one sample for each
+/// step they run.
+#[derive(Debug)]
+pub struct Profiler {
+    window: usize,
+    epoch: Instant,
+    frames: VecDeque<Frame>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    /// Keeps the last 120 frames
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_window(120)
+    }
+
+    /// Keeps the last `frames` frames
+    ///
+    /// # Panics
+    /// Panics if `frames` is zero
+    #[must_use]
+    pub fn with_window(frames: usize) -> Self {
+        assert!(frames > 0, "the profiler must keep at least one frame");
+        Self {
+            window: frames,
+            epoch: Instant::now(),
+            frames: VecDeque::with_capacity(frames),
+        }
+    }
+
+    pub(crate) fn record(&mut self, frame: Frame) {
+        if self.frames.len() == self.window {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// The recorded frames, oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter()
+    }
+
+    /// How long whole frames took, or `None` if none have been recorded
+    #[must_use]
+    pub fn frame(&self) -> Option<Stats> {
+        Stats::of(self.frames.iter().map(|frame| frame.duration).collect())
+    }
+
+    fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.frames.iter().flat_map(|frame| &frame.samples)
+    }
+
+    /// How long `module`'s ticks took, or `None` if it has not run in the window
+    #[must_use]
+    pub fn module(&self, module: ModuleId) -> Option<ModuleStats> {
+        let (ticks, waits) = self
+            .samples()
+            .filter(|sample| sample.module == module)
+            .map(|sample| (sample.duration, sample.lock_wait))
+            .unzip();
+        Some(ModuleStats {
+            tick: Stats::of(ticks)?,
+            lock_wait: Stats::of(waits)?,
+        })
+    }
+
+    /// Every module which has run in the window, slowest on average first
+    #[must_use]
+    pub fn modules(&self) -> Vec<(ModuleId, ModuleStats)> {
+        let mut samples: HashMap<ModuleId, (Vec<Duration>, Vec<Duration>)> = HashMap::new();
+        for sample in self.samples() {
+            let (ticks, waits) = samples.entry(sample.module).or_default();
+            ticks.push(sample.duration);
+            waits.push(sample.lock_wait);
+        }
+        let mut modules: Vec<(ModuleId, ModuleStats)> = samples
+            .into_iter()
+            .filter_map(|(module, (ticks, waits))| {
+                let stats = ModuleStats {
+                    tick: Stats::of(ticks)?,
+                    lock_wait: Stats::of(waits)?,
+                };
+                Some((module, stats))
+            })
+            .collect();
+        modules.sort_unstable_by_key(|(_, stats)| Reverse(stats.tick.avg));
+        modules
+    }
+
+    fn micros(&self, instant: Instant) -> u128 {
+        instant.saturating_duration_since(self.epoch).as_micros()
+    }
+
+    /// Writes the recorded frames in the Chrome trace event format, which can be opened in
+    /// `chrome://tracing` or Perfetto. Each rayon worker gets its own track, the thread which
+    /// called [`App::tick`](crate::App::tick) is track 0.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails
+    pub fn write_chrome_trace(&self, mut writer: impl Write) -> Result<(), Error> {
+        write!(writer, "{{\"traceEvents\":[")?;
+        let mut first = true;
+        for frame in &self.frames {
+            let separator = if first { "" } else { "," };
+            first = false;
+            write!(
+                writer,
+                "{separator}{{\"name\":\"frame\",\"cat\":\"frame\",\"ph\":\"X\",\"ts\":{},\
+                 \"dur\":{},\"pid\":0,\"tid\":0}}",
+                self.micros(frame.start),
+                frame.duration.as_micros(),
+            )?;
+            for sample in &frame.samples {
+                write!(
+                    writer,
+                    ",{{\"name\":\"{}\",\"cat\":\"module\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\
+                     \"pid\":0,\"tid\":{},\"args\":{{\"lock_wait_us\":{}}}}}",
+                    escape(&sample.module.to_string()),
+                    self.micros(sample.start),
+                    sample.duration.as_micros(),
+                    sample.thread.map_or(0, |thread| thread + 1),
+                    sample.lock_wait.as_micros(),
+                )?;
+            }
+        }
+        write!(writer, "]}}")?;
+        Ok(())
+    }
+}
+
+impl Module for Profiler {}
+
+/// Escapes a string for use inside a JSON string literal
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ =
+                    fmt::Write::write_fmt(&mut escaped, format_args!("\\u{:04x}", u32::from(c)));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Profiler;
+    use crate::{App, Module, ModuleId};
+
+    #[derive(Debug)]
+    struct Sleeper;
+
+    impl Module for Sleeper {
+        fn tick(&mut self, _: &App) {
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn test_profiler() {
+        let mut app = App::new();
+        app.register_module(Sleeper).unwrap();
+        app.register_module(Profiler::with_window(3)).unwrap();
+        for _ in 0..5 {
+            app.tick();
+        }
+
+        let profiler = app.get_module::<Profiler>().unwrap();
+        assert_eq!(profiler.frames().count(), 3);
+        let sleeper = profiler.module(ModuleId::of::<Sleeper>()).unwrap();
+        assert_eq!(sleeper.tick.count, 3);
+        assert!(sleeper.tick.min >= Duration::from_millis(2));
+        assert!(sleeper.tick.min <= sleeper.tick.p99);
+        assert!(sleeper.tick.p99 <= sleeper.tick.max);
+        assert_eq!(profiler.modules()[0].0, ModuleId::of::<Sleeper>());
+        assert!(profiler.frame().unwrap().min >= sleeper.tick.min);
+
+        let mut trace = Vec::new();
+        profiler.write_chrome_trace(&mut trace).unwrap();
+        drop(profiler);
+        let trace = String::from_utf8(trace).unwrap();
+        assert!(trace.starts_with("{\"traceEvents\":[{\"name\":\"frame\""));
+        assert!(trace.contains("\"name\":\"birb::profile::tests::Sleeper\""));
+        assert!(trace.ends_with("]}"));
+    }
+}
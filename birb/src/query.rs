@@ -0,0 +1,710 @@
+use rayon::prelude::*;
+use std::{
+    any::{type_name, TypeId},
+    marker::PhantomData,
+    mem,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    archetype::{Archetype, Archetypes},
+    component::{Component, Ticks},
+    entity::Entity,
+    lock::{ReadGuard, WriteGuard},
+};
+
+/// Archetypes with fewer rows than this are not split any further between rayon threads
+const MIN_SPLIT: usize = 1024;
+
+/// What a query fetches for each entity: `&T`, `&mut T`, [`Entity`], or a tuple of up to
+/// twelve of these
+pub trait QueryData {
+    type Item<'a>: Send;
+    /// The locks held on one archetype's columns for the lifetime of the query
+    type Guard<'a>;
+    /// A [`QueryData::Guard`] whose columns are still being locked
+    type Partial<'a>: Default;
+    /// A range of rows borrowed from a [`QueryData::Guard`]
+    type Slice<'a>: Default + Send;
+
+    /// The name of every component accessed, used to reject queries which access the same
+    /// component twice
+    fn components(components: &mut Vec<(TypeId, &'static str)>);
+
+    fn matches(archetype: &Archetype) -> bool;
+
+    /// Locks `component`'s column if it is accessed. A query locks its columns one at a time
+    /// in order of [`TypeId`], so queries naming the same components in a different order
+    /// can't deadlock.
+    fn lock<'a>(
+        archetype: &'a Archetype,
+        ticks: Ticks,
+        component: TypeId,
+        partial: &mut Self::Partial<'a>,
+    );
+
+    /// The guard, once every column accessed has been locked
+    fn finish<'a>(archetype: &'a Archetype, partial: Self::Partial<'a>) -> Self::Guard<'a>;
+
+    fn slice<'a>(guard: &'a mut Self::Guard<'_>) -> Self::Slice<'a>;
+
+    fn split_at(slice: Self::Slice<'_>, mid: usize) -> (Self::Slice<'_>, Self::Slice<'_>);
+
+    fn split_first(slice: Self::Slice<'_>) -> Option<(Self::Item<'_>, Self::Slice<'_>)>;
+
+    /// Marks the first `len` rows of `slice` as changed, if anything is accessed mutably
+    fn mark(slice: &Self::Slice<'_>, len: usize);
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'a> = &'a T;
+    type Guard<'a> = ReadGuard<'a, [T]>;
+    type Partial<'a> = Option<Self::Guard<'a>>;
+    type Slice<'a> = &'a [T];
+
+    fn components(components: &mut Vec<(TypeId, &'static str)>) {
+        components.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains(TypeId::of::<T>())
+    }
+
+    fn lock<'a>(
+        archetype: &'a Archetype,
+        _: Ticks,
+        component: TypeId,
+        partial: &mut Self::Partial<'a>,
+    ) {
+        if component == TypeId::of::<T>() {
+            let column = archetype.column(component).unwrap();
+            *partial = Some(ReadGuard::new(&column.data, column.info.name(), |column| {
+                column.downcast_slice().unwrap()
+            }));
+        }
+    }
+
+    fn finish<'a>(_: &'a Archetype, partial: Self::Partial<'a>) -> Self::Guard<'a> {
+        partial.unwrap()
+    }
+
+    fn slice<'a>(guard: &'a mut Self::Guard<'_>) -> Self::Slice<'a> {
+        guard
+    }
+
+    fn split_at(slice: Self::Slice<'_>, mid: usize) -> (Self::Slice<'_>, Self::Slice<'_>) {
+        slice.split_at(mid)
+    }
+
+    fn split_first(slice: Self::Slice<'_>) -> Option<(Self::Item<'_>, Self::Slice<'_>)> {
+        slice.split_first()
+    }
+
+    fn mark(_: &Self::Slice<'_>, _: usize) {}
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'a> = &'a mut T;
+    /// Along with the column, the change ticks of each row and the tick to mark them with
+    type Guard<'a> = (WriteGuard<'a, [T]>, &'a [AtomicU64], u64);
+    type Partial<'a> = Option<Self::Guard<'a>>;
+    type Slice<'a> = (&'a mut [T], &'a [AtomicU64], u64);
+
+    fn components(components: &mut Vec<(TypeId, &'static str)>) {
+        components.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains(TypeId::of::<T>())
+    }
+
+    fn lock<'a>(
+        archetype: &'a Archetype,
+        ticks: Ticks,
+        component: TypeId,
+        partial: &mut Self::Partial<'a>,
+    ) {
+        if component == TypeId::of::<T>() {
+            let column = archetype.column(component).unwrap();
+            let guard = WriteGuard::new(&column.data, column.info.name(), |column| {
+                column.downcast_slice_mut().unwrap()
+            });
+            *partial = Some((guard, &column.changed, ticks.this_run));
+        }
+    }
+
+    fn finish<'a>(_: &'a Archetype, partial: Self::Partial<'a>) -> Self::Guard<'a> {
+        partial.unwrap()
+    }
+
+    fn slice<'a>(guard: &'a mut Self::Guard<'_>) -> Self::Slice<'a> {
+        (&mut guard.0, guard.1, guard.2)
+    }
+
+    fn split_at(slice: Self::Slice<'_>, mid: usize) -> (Self::Slice<'_>, Self::Slice<'_>) {
+        let (values, changed, tick) = slice;
+        let (left, right) = values.split_at_mut(mid);
+        let (left_changed, right_changed) = changed.split_at(mid);
+        ((left, left_changed, tick), (right, right_changed, tick))
+    }
+
+    fn split_first(slice: Self::Slice<'_>) -> Option<(Self::Item<'_>, Self::Slice<'_>)> {
+        let (values, changed, tick) = slice;
+        let (value, values) = values.split_first_mut()?;
+        Some((value, (values, changed.get(1..)?, tick)))
+    }
+
+    fn mark((_, changed, tick): &Self::Slice<'_>, len: usize) {
+        for changed in &changed[..len] {
+            changed.store(*tick, Ordering::Relaxed);
+        }
+    }
+}
+
+impl QueryData for Entity {
+    type Item<'a> = Self;
+    type Guard<'a> = &'a [Self];
+    type Partial<'a> = ();
+    type Slice<'a> = &'a [Self];
+
+    fn components(_: &mut Vec<(TypeId, &'static str)>) {}
+
+    fn matches(_: &Archetype) -> bool {
+        true
+    }
+
+    fn lock(_: &Archetype, _: Ticks, _: TypeId, (): &mut Self::Partial<'_>) {}
+
+    fn finish<'a>(archetype: &'a Archetype, (): Self::Partial<'a>) -> Self::Guard<'a> {
+        &archetype.entities
+    }
+
+    fn slice<'a>(guard: &'a mut Self::Guard<'_>) -> Self::Slice<'a> {
+        guard
+    }
+
+    fn split_at(slice: Self::Slice<'_>, mid: usize) -> (Self::Slice<'_>, Self::Slice<'_>) {
+        slice.split_at(mid)
+    }
+
+    fn split_first(slice: Self::Slice<'_>) -> Option<(Self::Item<'_>, Self::Slice<'_>)> {
+        slice.split_first().map(|(entity, rest)| (*entity, rest))
+    }
+
+    fn mark(_: &Self::Slice<'_>, _: usize) {}
+}
+
+macro_rules! tuple_query_data {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type Item<'a> = ($($name::Item<'a>,)*);
+            type Guard<'a> = ($($name::Guard<'a>,)*);
+            type Partial<'a> = ($($name::Partial<'a>,)*);
+            type Slice<'a> = ($($name::Slice<'a>,)*);
+
+            fn components(components: &mut Vec<(TypeId, &'static str)>) {
+                $($name::components(components);)*
+            }
+
+            fn matches(archetype: &Archetype) -> bool {
+                $($name::matches(archetype))&&*
+            }
+
+            fn lock<'a>(
+                archetype: &'a Archetype,
+                ticks: Ticks,
+                component: TypeId,
+                partial: &mut Self::Partial<'a>,
+            ) {
+                let ($($name,)*) = partial;
+                $($name::lock(archetype, ticks, component, $name);)*
+            }
+
+            fn finish<'a>(archetype: &'a Archetype, partial: Self::Partial<'a>) -> Self::Guard<'a> {
+                let ($($name,)*) = partial;
+                ($($name::finish(archetype, $name),)*)
+            }
+
+            fn slice<'a>(guard: &'a mut Self::Guard<'_>) -> Self::Slice<'a> {
+                let ($($name,)*) = guard;
+                ($($name::slice($name),)*)
+            }
+
+            fn split_at(slice: Self::Slice<'_>, mid: usize) -> (Self::Slice<'_>, Self::Slice<'_>) {
+                let ($($name,)*) = slice;
+                $(let $name = $name::split_at($name, mid);)*
+                (($($name.0,)*), ($($name.1,)*))
+            }
+
+            fn split_first(slice: Self::Slice<'_>) -> Option<(Self::Item<'_>, Self::Slice<'_>)> {
+                let ($($name,)*) = slice;
+                $(let $name = $name::split_first($name)?;)*
+                Some((($($name.0,)*), ($($name.1,)*)))
+            }
+
+            fn mark(slice: &Self::Slice<'_>, len: usize) {
+                let ($($name,)*) = slice;
+                $($name::mark($name, len);)*
+            }
+        }
+    };
+}
+
+tuple_query_data!(A);
+tuple_query_data!(A, B);
+tuple_query_data!(A, B, C);
+tuple_query_data!(A, B, C, D);
+tuple_query_data!(A, B, C, D, E);
+tuple_query_data!(A, B, C, D, E, F);
+tuple_query_data!(A, B, C, D, E, F, G);
+tuple_query_data!(A, B, C, D, E, F, G, H);
+tuple_query_data!(A, B, C, D, E, F, G, H, I);
+tuple_query_data!(A, B, C, D, E, F, G, H, I, J);
+tuple_query_data!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_query_data!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Narrows which entities a query visits without fetching anything from them: [`With`],
+/// [`Without`], [`Changed`], `()` to visit everything, or a tuple which must all match
+pub trait QueryFilter {
+    /// What the filter needs from one archetype to decide on each of its rows
+    type State<'a>: Copy + Send;
+
+    /// Whether [`QueryFilter::keep`] can reject individual rows, rather than always keeping
+    /// every row of a matching archetype
+    const PER_ROW: bool;
+
+    fn matches(archetype: &Archetype) -> bool;
+
+    fn prepare(archetype: &Archetype, ticks: Ticks) -> Self::State<'_>;
+
+    /// Whether to visit `row` of an archetype which [`QueryFilter::matches`]
+    fn keep(state: Self::State<'_>, row: usize) -> bool;
+}
+
+/// Only visits entities which have a `T`
+pub struct With<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    type State<'a> = ();
+
+    const PER_ROW: bool = false;
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains(TypeId::of::<T>())
+    }
+
+    fn prepare(_: &Archetype, _: Ticks) -> Self::State<'_> {}
+
+    fn keep((): Self::State<'_>, _: usize) -> bool {
+        true
+    }
+}
+
+/// Only visits entities which do not have a `T`
+pub struct Without<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+    type State<'a> = ();
+
+    const PER_ROW: bool = false;
+
+    fn matches(archetype: &Archetype) -> bool {
+        !archetype.contains(TypeId::of::<T>())
+    }
+
+    fn prepare(_: &Archetype, _: Ticks) -> Self::State<'_> {}
+
+    fn keep((): Self::State<'_>, _: usize) -> bool {
+        true
+    }
+}
+
+/// Only visits entities whose `T` has been mutably accessed since the module running the query
+/// last ran. Outside of a module, visits every entity whose `T` has ever been.
+pub struct Changed<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type State<'a> = (&'a [AtomicU64], Ticks);
+
+    const PER_ROW: bool = true;
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains(TypeId::of::<T>())
+    }
+
+    fn prepare(archetype: &Archetype, ticks: Ticks) -> Self::State<'_> {
+        let column = archetype.column(TypeId::of::<T>()).unwrap();
+        (&column.changed, ticks)
+    }
+
+    fn keep((changed, ticks): Self::State<'_>, row: usize) -> bool {
+        ticks.is_changed(changed[row].load(Ordering::Relaxed))
+    }
+}
+
+macro_rules! tuple_query_filter {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type State<'a> = ($($name::State<'a>,)*);
+
+            const PER_ROW: bool = false $(|| $name::PER_ROW)*;
+
+            #[allow(unused_variables)]
+            fn matches(archetype: &Archetype) -> bool {
+                true $(&& $name::matches(archetype))*
+            }
+
+            #[allow(unused_variables, clippy::unused_unit)]
+            fn prepare(archetype: &Archetype, ticks: Ticks) -> Self::State<'_> {
+                ($($name::prepare(archetype, ticks),)*)
+            }
+
+            #[allow(unused_variables)]
+            fn keep(state: Self::State<'_>, row: usize) -> bool {
+                let ($($name,)*) = state;
+                true $(&& $name::keep($name, row))*
+            }
+        }
+    };
+}
+
+tuple_query_filter!();
+tuple_query_filter!(A);
+tuple_query_filter!(A, B);
+tuple_query_filter!(A, B, C);
+tuple_query_filter!(A, B, C, D);
+tuple_query_filter!(A, B, C, D, E);
+tuple_query_filter!(A, B, C, D, E, F);
+tuple_query_filter!(A, B, C, D, E, F, G);
+tuple_query_filter!(A, B, C, D, E, F, G, H);
+tuple_query_filter!(A, B, C, D, E, F, G, H, I);
+tuple_query_filter!(A, B, C, D, E, F, G, H, I, J);
+tuple_query_filter!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_query_filter!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Every entity matching `Q` and `F`, with the columns it needs locked until dropped
+pub struct Query<'a, Q: QueryData, F: QueryFilter = ()> {
+    guards: Vec<(Q::Guard<'a>, F::State<'a>, usize)>,
+}
+
+impl<'a, Q: QueryData, F: QueryFilter> Query<'a, Q, F> {
+    /// # Panics
+    /// Panics if `Q` accesses the same component more than once
+    pub(crate) fn new(archetypes: &'a Archetypes, ticks: Ticks) -> Self {
+        let mut components = Vec::new();
+        Q::components(&mut components);
+        components.sort_unstable_by_key(|(id, _)| *id);
+        if let Some(pair) = components.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            panic!("{} appears more than once in the query", pair[0].1);
+        }
+
+        let guards = archetypes
+            .iter()
+            .filter(|archetype| {
+                archetype.len() > 0 && Q::matches(archetype) && F::matches(archetype)
+            })
+            .map(|archetype| {
+                let mut partial = Q::Partial::default();
+                for &(component, _) in &components {
+                    Q::lock(archetype, ticks, component, &mut partial);
+                }
+                (
+                    Q::finish(archetype, partial),
+                    F::prepare(archetype, ticks),
+                    archetype.len(),
+                )
+            })
+            .collect();
+        Self { guards }
+    }
+
+    /// The number of entities in the archetypes matched, before per-entity filters such as
+    /// [`Changed`] are applied
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.guards.iter().map(|(_, _, len)| len).sum()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.guards.is_empty()
+    }
+
+    pub fn iter(&mut self) -> impl Iterator<Item = Q::Item<'_>> + use<'_, 'a, Q, F> {
+        self.chunks().flat_map(Chunk::marked)
+    }
+
+    pub fn par_iter(&mut self) -> impl ParallelIterator<Item = Q::Item<'_>> + use<'_, 'a, Q, F> {
+        self.chunks()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .flat_map(|chunk| rayon::iter::split(chunk, Chunk::split))
+            .flat_map_iter(Chunk::marked)
+    }
+
+    fn chunks(&mut self) -> impl Iterator<Item = Chunk<'_, 'a, Q, F>> + use<'_, 'a, Q, F> {
+        self.guards.iter_mut().map(|(guard, filter, len)| Chunk {
+            slice: Q::slice(guard),
+            filter: *filter,
+            row: 0,
+            len: *len,
+        })
+    }
+}
+
+/// A contiguous range of rows from one archetype
+struct Chunk<'s, 'a, Q: QueryData, F: QueryFilter> {
+    slice: Q::Slice<'s>,
+    filter: F::State<'a>,
+    /// The archetype row of the start of `slice`
+    row: usize,
+    len: usize,
+}
+
+impl<Q: QueryData, F: QueryFilter> Chunk<'_, '_, Q, F> {
+    /// Marks every row as changed up front when the filter keeps them all, which is much
+    /// cheaper than marking each row as it is visited
+    fn marked(self) -> Self {
+        if !F::PER_ROW {
+            Q::mark(&self.slice, self.len);
+        }
+        self
+    }
+
+    fn split(self) -> (Self, Option<Self>) {
+        if self.len < MIN_SPLIT * 2 {
+            return (self, None);
+        }
+        let mid = self.len / 2;
+        let (left, right) = Q::split_at(self.slice, mid);
+        (
+            Self {
+                slice: left,
+                filter: self.filter,
+                row: self.row,
+                len: mid,
+            },
+            Some(Self {
+                slice: right,
+                filter: self.filter,
+                row: self.row + mid,
+                len: self.len - mid,
+            }),
+        )
+    }
+}
+
+impl<'s, Q: QueryData, F: QueryFilter> Iterator for Chunk<'s, '_, Q, F> {
+    type Item = Q::Item<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.len > 0 {
+            let slice = mem::take(&mut self.slice);
+            let row = self.row;
+            self.row += 1;
+            self.len -= 1;
+            if F::keep(self.filter, row) {
+                if F::PER_ROW {
+                    Q::mark(&slice, 1);
+                }
+                let (item, rest) = Q::split_first(slice)?;
+                self.slice = rest;
+                return Some(item);
+            }
+            // Skipped rows are split off rather than fetched, so they aren't marked as changed
+            self.slice = Q::split_at(slice, 1).1;
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use crate::{
+        entity::Entity,
+        schedule::{every, Schedule},
+        App, Module, ModuleId,
+    };
+
+    use super::{Changed, With, Without};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Velocity(i32);
+    struct Frozen;
+
+    #[test]
+    fn test_query() {
+        let mut app = App::new();
+        let a = app.spawn((Position(0), Velocity(1)));
+        let b = app.spawn((Velocity(2), Position(0), Frozen));
+        let c = app.spawn((Position(0),));
+        app.spawn_batch((0..5000).map(|_| (Position(0), Velocity(3))));
+
+        let mut query = app.query::<(&mut Position, &Velocity), Without<Frozen>>();
+        assert_eq!(query.len(), 5001);
+        query
+            .iter()
+            .for_each(|(position, velocity)| position.0 += velocity.0);
+        query
+            .par_iter()
+            .for_each(|(position, velocity)| position.0 += velocity.0);
+        drop(query);
+
+        assert_eq!(*app.get::<Position>(a).unwrap(), Position(2));
+        assert_eq!(*app.get::<Position>(b).unwrap(), Position(0));
+        assert_eq!(*app.get::<Position>(c).unwrap(), Position(0));
+        assert!(app
+            .query::<&Position, Without<Velocity>>()
+            .iter()
+            .all(|position| position.0 == 0));
+        assert_eq!(
+            app.query::<&Position, ()>()
+                .par_iter()
+                .filter(|position| position.0 == 6)
+                .count(),
+            5000
+        );
+
+        let mut frozen = app.query::<(Entity, &Velocity), With<Frozen>>();
+        assert_eq!(frozen.iter().collect::<Vec<_>>(), [(b, &Velocity(2))]);
+    }
+
+    #[test]
+    #[should_panic = "appears more than once"]
+    fn test_conflicting_access() {
+        let mut app = App::new();
+        app.spawn((Position(0),));
+        let _ = app.query::<(&mut Position, &Position), ()>();
+    }
+
+    #[test]
+    fn test_lock_order() {
+        let mut app = App::new();
+        app.spawn_batch((0..100).map(|_| (Position(0), Velocity(0))));
+        let world: &App = &app;
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    world
+                        .query::<(&mut Position, &Velocity), ()>()
+                        .iter()
+                        .for_each(|(position, _)| position.0 += 1);
+                }
+            });
+            for _ in 0..1000 {
+                world
+                    .query::<(&mut Velocity, &Position), ()>()
+                    .iter()
+                    .for_each(|(velocity, position)| velocity.0 = position.0);
+            }
+        });
+        assert!(app
+            .query::<&Position, ()>()
+            .iter()
+            .all(|position| position.0 == 1000));
+    }
+
+    /// Moves one entity each tick
+    #[derive(Debug)]
+    struct Mover(Entity);
+
+    impl Module for Mover {
+        fn tick(&mut self, app: &App) {
+            app.get_mut::<Position>(self.0).unwrap().0 += 1;
+        }
+    }
+
+    /// Records how many positions changed since it last ran
+    #[derive(Debug, Default)]
+    struct Sync(Vec<usize>);
+
+    impl Module for Sync {
+        fn tick(&mut self, app: &App) {
+            let mut changed = app.query::<&Position, Changed<Position>>();
+            self.0.push(changed.iter().count());
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().after::<Mover>()
+        }
+    }
+
+    #[test]
+    fn test_changed() {
+        let mut app = App::new();
+        let a = app.spawn((Position(0),));
+        let b = app.spawn((Position(0), Velocity(0)));
+        app.spawn((Position(0),));
+        app.register_module(Mover(a)).unwrap();
+        app.register_module(Sync::default()).unwrap();
+
+        app.tick();
+        app.tick();
+        app.query::<(&mut Position, &Velocity), ()>()
+            .iter()
+            .for_each(|(position, velocity)| position.0 += velocity.0);
+        app.tick();
+        app.get_entity_mut::<Position>().unwrap();
+        app.tick();
+        assert_eq!(app.get_module::<Sync>().unwrap().0, [3, 1, 2, 3]);
+        assert!(app.is_changed::<Position>(b).unwrap());
+
+        let sync = ModuleId::of::<Sync>();
+        app.get_module::<Sync>().unwrap();
+        assert!(!app.is_module_changed(sync).unwrap());
+        app.get_module_mut::<Sync>().unwrap();
+        assert!(app.is_module_changed(sync).unwrap());
+    }
+
+    /// Moves one entity on every second tick, starting with the second
+    #[derive(Debug)]
+    struct Stutter(Entity, u64);
+
+    impl Module for Stutter {
+        fn tick(&mut self, app: &App) {
+            self.1 += 1;
+            if self.1.is_multiple_of(2) {
+                app.get_mut::<Position>(self.0).unwrap().0 += 1;
+            }
+        }
+    }
+
+    /// Records how many positions changed, running only on every second tick
+    #[derive(Debug, Default)]
+    struct Sometimes(Vec<usize>);
+
+    impl Module for Sometimes {
+        fn tick(&mut self, app: &App) {
+            let mut changed = app.query::<&Position, Changed<Position>>();
+            self.0.push(changed.iter().count());
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().after::<Stutter>().run_if(every(2))
+        }
+    }
+
+    #[test]
+    fn test_changed_run_condition() {
+        let mut app = App::new();
+        let a = app.spawn((Position(0),));
+        app.spawn((Position(0),));
+        app.register_module(Stutter(a, 0)).unwrap();
+        app.register_module(Sometimes::default()).unwrap();
+
+        for _ in 0..5 {
+            app.tick();
+        }
+        assert_eq!(app.get_module::<Sometimes>().unwrap().0, [2, 1, 1]);
+    }
+}
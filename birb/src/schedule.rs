@@ -0,0 +1,575 @@
+use std::{
+    any::{type_name, TypeId},
+    collections::{HashMap, HashSet},
+    fmt::{self, Debug, Display},
+    sync::Arc,
+};
+
+use crate::{component::Component, App, Module, ModuleId};
+
+/// Named phases of a tick. Every module runs in exactly one stage and stages run in the order
+/// they are declared here.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    PreUpdate,
+    /// Runs zero or more times per tick, once per fixed timestep which has passed, see
+    /// [`FixedTime`](crate::timestep::FixedTime)
+    FixedUpdate,
+    #[default]
+    Update,
+    PostUpdate,
+}
+
+impl Stage {
+    pub const ALL: [Self; 4] = [
+        Self::PreUpdate,
+        Self::FixedUpdate,
+        Self::Update,
+        Self::PostUpdate,
+    ];
+}
+
+/// Describes where a module runs within a tick.
+///
+/// Returned from [`Module::schedule`] when the module is registered.
+#[derive(Debug, Default, Clone)]
+pub struct Schedule {
+    stage: Stage,
+    before: Vec<Label>,
+    after: Vec<Label>,
+    access: Option<Access>,
+    conditions: Vec<Condition>,
+}
+
+impl Schedule {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn in_stage(mut self, stage: Stage) -> Self {
+        self.stage = stage;
+        self
+    }
+
+    /// The module must finish ticking before `T` starts
+    #[must_use]
+    pub fn before<T: Module>(mut self) -> Self {
+        self.before.push(Label::of::<T>());
+        self
+    }
+
+    /// The module must not start ticking until `T` has finished
+    #[must_use]
+    pub fn after<T: Module>(mut self) -> Self {
+        self.after.push(Label::of::<T>());
+        self
+    }
+
+    /// Declares that the module reads the component `T` while ticking
+    #[must_use]
+    pub fn reads<T: Component>(mut self) -> Self {
+        self.access()
+            .reads
+            .push(Resource::Component(Label::of::<T>()));
+        self
+    }
+
+    /// Declares that the module writes the component `T` while ticking
+    #[must_use]
+    pub fn writes<T: Component>(mut self) -> Self {
+        self.access()
+            .writes
+            .push(Resource::Component(Label::of::<T>()));
+        self
+    }
+
+    /// Declares that the module reads module `T` while ticking
+    #[must_use]
+    pub fn reads_module<T: Module>(mut self) -> Self {
+        self.access().reads.push(Resource::Module(Label::of::<T>()));
+        self
+    }
+
+    /// Declares that the module writes module `T` while ticking
+    #[must_use]
+    pub fn writes_module<T: Module>(mut self) -> Self {
+        self.access()
+            .writes
+            .push(Resource::Module(Label::of::<T>()));
+        self
+    }
+
+    /// Skips the module's tick whenever `condition` returns `false`. Conditions are checked
+    /// before every tick, so a module in [`Stage::FixedUpdate`] checks them once per step, and
+    /// the module only runs if all of them hold. Events a condition reads are still there for
+    /// the module to read when it ticks.
+    #[must_use]
+    pub fn run_if(mut self, condition: impl Fn(&App) -> bool + Send + Sync + 'static) -> Self {
+        self.conditions.push(Condition(Arc::new(condition)));
+        self
+    }
+
+    #[must_use]
+    pub const fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Whether every run condition currently holds
+    pub(crate) fn should_run(&self, app: &App) -> bool {
+        self.conditions.iter().all(|condition| (condition.0)(app))
+    }
+
+    fn access(&mut self) -> &mut Access {
+        self.access.get_or_insert_with(Access::default)
+    }
+}
+
+#[derive(Clone)]
+struct Condition(Arc<dyn Fn(&App) -> bool + Send + Sync>);
+
+impl Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Condition")
+    }
+}
+
+/// A run condition which holds on every `n`th tick, starting with the first
+///
+/// # Panics
+/// Panics if `n` is zero
+pub fn every(n: u64) -> impl Fn(&App) -> bool + Send + Sync + 'static {
+    assert!(n > 0, "a module cannot run every 0 ticks");
+    move |app| app.ticks() % n == 0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resource {
+    Component(Label),
+    Module(Label),
+}
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Component(label) => write!(f, "component {}", label.name),
+            Self::Module(label) => write!(f, "module {}", label.name),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct Access {
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+}
+
+impl Access {
+    /// Modules which don't declare their access are assumed not to conflict with anything, and
+    /// rely on the locks taken at runtime instead
+    fn conflicts(this: (Resource, Option<&Self>), other: (Resource, Option<&Self>)) -> bool {
+        let (Some(a), Some(b)) = (this.1, other.1) else {
+            return false;
+        };
+        // A ticking module holds its own lock for writing
+        let a_writes = || a.writes.iter().chain(std::iter::once(&this.0));
+        let b_writes = || b.writes.iter().chain(std::iter::once(&other.0));
+        let a_touches = || a.reads.iter().chain(a_writes());
+        let b_touches = || b.reads.iter().chain(b_writes());
+        a_writes().any(|resource| b_touches().any(|x| x == resource))
+            || b_writes().any(|resource| a_touches().any(|x| x == resource))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Label {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl Label {
+    fn of<T: 'static>() -> Self {
+        Self {
+            id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The ordering constraints form a cycle, listed in execution order
+    Cycle(Vec<&'static str>),
+    /// `before` is required to run before `after`, but is in a later stage
+    StageOrder {
+        before: &'static str,
+        after: &'static str,
+    },
+    /// A module with the same type and label is already registered
+    Duplicate(ModuleId),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cycle(cycle) => {
+                write!(f, "module ordering cycle: ")?;
+                for name in cycle {
+                    write!(f, "{name} -> ")?;
+                }
+                write!(f, "{}", cycle.first().unwrap_or(&""))
+            }
+            Self::StageOrder { before, after } => {
+                write!(
+                    f,
+                    "{before} must run before {after} but is in a later stage"
+                )
+            }
+            Self::Duplicate(module) => write!(f, "{module} is already registered"),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+/// A module as seen by the scheduler
+pub(crate) struct Node<'a> {
+    pub id: ModuleId,
+    pub schedule: &'a Schedule,
+}
+
+impl Node<'_> {
+    /// Every instance of a module type is treated as the same resource
+    const fn resource(&self) -> Resource {
+        Resource::Module(Label {
+            id: self.id.type_id(),
+            name: self.id.name(),
+        })
+    }
+
+    fn conflicts(&self, other: &Self) -> bool {
+        Access::conflicts(
+            (self.resource(), self.schedule.access.as_ref()),
+            (other.resource(), other.schedule.access.as_ref()),
+        )
+    }
+}
+
+/// The compiled execution order of every registered module.
+///
+/// Each stage is a list of batches which run one after the other, the modules within a batch have
+/// neither ordering constraints nor conflicting access between them and are run in parallel.
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub(crate) stages: Vec<(Stage, Vec<Vec<ModuleId>>)>,
+    steps: HashMap<ModuleId, Option<Access>>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (stage, batches) in &self.stages {
+            writeln!(f, "{stage:?}")?;
+            for (i, batch) in batches.iter().enumerate() {
+                writeln!(f, "  batch {i}")?;
+                for id in batch {
+                    write!(f, "    {id}")?;
+                    match &self.steps[id] {
+                        Some(access) => {
+                            for resource in &access.reads {
+                                write!(f, " [reads {resource}]")?;
+                            }
+                            for resource in &access.writes {
+                                write!(f, " [writes {resource}]")?;
+                            }
+                            writeln!(f)?;
+                        }
+                        None => writeln!(f, " [undeclared access]")?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Plan {
+    /// Builds a plan from `nodes`, which must be in registration order so that the output is
+    /// deterministic
+    pub(crate) fn build(nodes: &[Node]) -> Result<Self, ScheduleError> {
+        // Constraints apply to every instance of a module type
+        let mut index: HashMap<TypeId, Vec<usize>> = HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            index.entry(node.id.type_id()).or_default().push(i);
+        }
+        let instances = |label: &Label| index.get(&label.id).into_iter().flatten().copied();
+
+        // Constraints on modules which are not registered are ignored
+        let mut edges = vec![Vec::new(); nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            let before = (node.schedule.before.iter())
+                .flat_map(|label| instances(label).map(move |to| (i, to)));
+            let after = (node.schedule.after.iter())
+                .flat_map(|label| instances(label).map(move |from| (from, i)));
+            for (from, to) in before.chain(after).filter(|(from, to)| from != to) {
+                let (from_stage, to_stage) = (nodes[from].schedule.stage, nodes[to].schedule.stage);
+                if from_stage > to_stage {
+                    return Err(ScheduleError::StageOrder {
+                        before: nodes[from].id.name(),
+                        after: nodes[to].id.name(),
+                    });
+                }
+                if from_stage == to_stage && !edges[from].contains(&to) {
+                    edges[from].push(to);
+                }
+            }
+        }
+
+        let mut stages = Vec::new();
+        for stage in Stage::ALL {
+            let members: Vec<usize> = (0..nodes.len())
+                .filter(|i| nodes[*i].schedule.stage == stage)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+
+            let mut batches = Vec::new();
+            let mut done = HashSet::new();
+            let mut remaining = members;
+            while !remaining.is_empty() {
+                // Greedily fill the batch with every module whose predecessors have all finished
+                // and which doesn't conflict with anything already in the batch
+                let mut batch: Vec<usize> = Vec::new();
+                for candidate in &remaining {
+                    let ready = (0..nodes.len())
+                        .filter(|from| edges[*from].contains(candidate))
+                        .all(|from| done.contains(&from));
+                    if ready
+                        && !batch
+                            .iter()
+                            .any(|i| nodes[*i].conflicts(&nodes[*candidate]))
+                    {
+                        batch.push(*candidate);
+                    }
+                }
+                if batch.is_empty() {
+                    break;
+                }
+                remaining.retain(|i| !batch.contains(i));
+                done.extend(batch.iter().copied());
+                batches.push(batch.iter().map(|i| nodes[*i].id).collect());
+            }
+
+            if !remaining.is_empty() {
+                let blocked: HashSet<usize> = remaining.into_iter().collect();
+                return Err(ScheduleError::Cycle(
+                    find_cycle(&edges, &blocked)
+                        .into_iter()
+                        .map(|i| nodes[i].id.name())
+                        .collect(),
+                ));
+            }
+
+            stages.push((stage, batches));
+        }
+
+        let steps = nodes
+            .iter()
+            .map(|node| (node.id, node.schedule.access.clone()))
+            .collect();
+        Ok(Self { stages, steps })
+    }
+}
+
+/// Every blocked node has an incoming edge from another blocked node, so walking those edges
+/// backwards must eventually revisit a node
+fn find_cycle(edges: &[Vec<usize>], blocked: &HashSet<usize>) -> Vec<usize> {
+    let mut path: Vec<usize> = Vec::new();
+    let Some(mut current) = blocked.iter().min().copied() else {
+        return path;
+    };
+    loop {
+        if let Some(start) = path.iter().position(|i| *i == current) {
+            let mut cycle = path.split_off(start);
+            cycle.reverse();
+            return cycle;
+        }
+        path.push(current);
+        let mut predecessors = (0..edges.len())
+            .filter(|from| blocked.contains(from) && edges[*from].contains(&current));
+        match predecessors.next() {
+            Some(previous) => current = previous,
+            None => return path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{App, Error};
+
+    #[derive(Debug)]
+    struct Input;
+    #[derive(Debug)]
+    struct Physics;
+    #[derive(Debug)]
+    struct Render;
+
+    impl Module for Input {}
+
+    impl Module for Physics {
+        fn schedule(&self) -> Schedule {
+            Schedule::new().after::<Input>().before::<Render>()
+        }
+    }
+
+    impl Module for Render {
+        fn schedule(&self) -> Schedule {
+            Schedule::new().in_stage(Stage::PostUpdate)
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut app = App::new();
+        app.register_module(Physics).unwrap();
+        app.register_module(Render).unwrap();
+        app.register_module(Input).unwrap();
+        assert_eq!(
+            app.plan.stages,
+            vec![
+                (
+                    Stage::Update,
+                    vec![
+                        vec![ModuleId::of::<Input>()],
+                        vec![ModuleId::of::<Physics>()]
+                    ]
+                ),
+                (Stage::PostUpdate, vec![vec![ModuleId::of::<Render>()]]),
+            ]
+        );
+    }
+
+    #[derive(Debug)]
+    struct A;
+    #[derive(Debug)]
+    struct B;
+
+    impl Module for A {
+        fn schedule(&self) -> Schedule {
+            Schedule::new().before::<B>()
+        }
+    }
+
+    impl Module for B {
+        fn schedule(&self) -> Schedule {
+            Schedule::new().before::<A>()
+        }
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut app = App::new();
+        app.register_module(A).unwrap();
+        assert!(matches!(
+            app.register_module(B),
+            Err(Error::Schedule(ScheduleError::Cycle(cycle)))
+                if cycle == [type_name::<B>(), type_name::<A>()]
+        ));
+        assert!(app.get_module::<B>().is_err());
+    }
+
+    #[derive(Debug, Default)]
+    struct Counter<const N: usize>(u32);
+
+    impl Module for Counter<0> {
+        fn tick(&mut self, _: &App) {
+            self.0 += 1;
+        }
+    }
+
+    impl Module for Counter<1> {
+        fn tick(&mut self, _: &App) {
+            self.0 += 1;
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().run_if(every(3))
+        }
+    }
+
+    impl Module for Counter<2> {
+        fn tick(&mut self, _: &App) {
+            self.0 += 1;
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new()
+                .after::<Counter<0>>()
+                .run_if(|app| app.get_module::<Counter<0>>().unwrap().0 % 2 == 0)
+        }
+    }
+
+    #[test]
+    fn test_run_conditions() {
+        let mut app = App::new();
+        app.register_module(Counter::<0>::default()).unwrap();
+        app.register_module(Counter::<1>::default()).unwrap();
+        app.register_module(Counter::<2>::default()).unwrap();
+        for _ in 0..6 {
+            app.tick();
+        }
+        assert_eq!(app.get_module::<Counter<1>>().unwrap().0, 2);
+        assert_eq!(app.get_module::<Counter<2>>().unwrap().0, 3);
+
+        let id = ModuleId::of::<Counter<0>>();
+        app.set_enabled(id, false).unwrap();
+        assert!(!app.is_enabled(id).unwrap());
+        app.tick();
+        assert_eq!(app.get_module::<Counter<0>>().unwrap().0, 6);
+        app.commands().set_enabled(id, true);
+        app.tick();
+        app.tick();
+        assert_eq!(app.get_module::<Counter<0>>().unwrap().0, 7);
+        assert!(app.is_enabled(id).unwrap());
+    }
+
+    #[derive(Debug)]
+    struct Writer<const N: usize>;
+    #[derive(Debug)]
+    struct Reader<const N: usize>;
+
+    impl<const N: usize> Module for Writer<N> {
+        fn schedule(&self) -> Schedule {
+            Schedule::new().writes::<u32>()
+        }
+    }
+
+    impl<const N: usize> Module for Reader<N> {
+        fn schedule(&self) -> Schedule {
+            Schedule::new().reads::<u32>()
+        }
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut app = App::new();
+        app.register_module(Reader::<0>).unwrap();
+        app.register_module(Writer::<0>).unwrap();
+        app.register_module(Writer::<1>).unwrap();
+        app.register_module(Reader::<1>).unwrap();
+        assert_eq!(
+            app.plan.stages,
+            vec![(
+                Stage::Update,
+                vec![
+                    vec![ModuleId::of::<Reader<0>>(), ModuleId::of::<Reader<1>>()],
+                    vec![ModuleId::of::<Writer<0>>()],
+                    vec![ModuleId::of::<Writer<1>>()],
+                ]
+            )]
+        );
+    }
+}
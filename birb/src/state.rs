@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::Hash,
+    sync::Arc,
+};
+
+use crate::{App, Module};
+
+/// Any type which can be used as the value of a [`State`]
+pub trait States: 'static + Clone + Eq + Hash + Debug + Send + Sync {}
+
+impl<T: 'static + Clone + Eq + Hash + Debug + Send + Sync> States for T {}
+
+pub(crate) type Hook = Arc<dyn Fn(&mut App) + Send + Sync>;
+
+/// A high-level state of the app, such as whether it is in a menu or playing, registered as a
+/// module.
+///
+/// Transitions are queued with [`Commands::set_state`](crate::command::Commands::set_state) and
+/// applied between ticks, so every module in a tick sees the same state. Modules can be limited
+/// to some states with the [`in_state`] run condition.
+pub struct State<S: States> {
+    current: S,
+    enter: HashMap<S, Vec<Hook>>,
+    exit: HashMap<S, Vec<Hook>>,
+}
+
+impl<S: States> State<S> {
+    /// Starts in `initial`, without running its enter hooks
+    #[must_use]
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            enter: HashMap::new(),
+            exit: HashMap::new(),
+        }
+    }
+
+    /// Runs `hook` whenever the app transitions into `state`
+    #[must_use]
+    pub fn on_enter(mut self, state: S, hook: impl Fn(&mut App) + Send + Sync + 'static) -> Self {
+        self.enter.entry(state).or_default().push(Arc::new(hook));
+        self
+    }
+
+    /// Runs `hook` whenever the app transitions out of `state`, before the state changes
+    #[must_use]
+    pub fn on_exit(mut self, state: S, hook: impl Fn(&mut App) + Send + Sync + 'static) -> Self {
+        self.exit.entry(state).or_default().push(Arc::new(hook));
+        self
+    }
+
+    #[must_use]
+    pub const fn get(&self) -> &S {
+        &self.current
+    }
+
+    /// The hooks to run when moving to `next`, or `None` if already in it
+    pub(crate) fn transition(&self, next: &S) -> Option<(Vec<Hook>, Vec<Hook>)> {
+        if self.current == *next {
+            return None;
+        }
+        let hooks =
+            |hooks: &HashMap<S, Vec<Hook>>, state| hooks.get(state).cloned().unwrap_or_default();
+        Some((hooks(&self.exit, &self.current), hooks(&self.enter, next)))
+    }
+
+    pub(crate) fn set(&mut self, next: S) {
+        self.current = next;
+    }
+}
+
+impl<S: States> Debug for State<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("State").field(&self.current).finish()
+    }
+}
+
+impl<S: States> Module for State<S> {}
+
+/// A run condition which holds while the app's [`State<S>`] is `state`
+pub fn in_state<S: States>(state: S) -> impl Fn(&App) -> bool + Send + Sync + 'static {
+    move |app| {
+        app.get_module::<State<S>>()
+            .is_ok_and(|current| *current.get() == state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::{in_state, State};
+    use crate::{schedule::Schedule, App, Module};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Game {
+        Menu,
+        Playing,
+    }
+
+    static LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    #[derive(Debug)]
+    struct Menu;
+
+    impl Module for Menu {
+        fn tick(&mut self, app: &App) {
+            app.commands().set_state(Game::Playing);
+            let state = app.get_module::<State<Game>>().unwrap().get().clone();
+            LOG.lock().push(format!("menu sees {state:?}"));
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().run_if(in_state(Game::Menu))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Player;
+
+    impl Module for Player {
+        fn tick(&mut self, _: &App) {
+            LOG.lock().push("player".to_string());
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().run_if(in_state(Game::Playing))
+        }
+    }
+
+    #[test]
+    fn test_state() {
+        let mut app = App::new();
+        app.register_module(
+            State::new(Game::Menu)
+                .on_exit(Game::Menu, |app| {
+                    let state = app.get_module::<State<Game>>().unwrap().get().clone();
+                    LOG.lock().push(format!("exit {state:?}"));
+                })
+                .on_enter(Game::Playing, |_| LOG.lock().push("enter".to_string())),
+        )
+        .unwrap();
+        app.register_module(Menu).unwrap();
+        app.register_module(Player).unwrap();
+
+        app.tick();
+        app.tick();
+        app.set_state(Game::Playing).unwrap();
+        assert_eq!(
+            *LOG.lock(),
+            ["menu sees Menu", "exit Menu", "enter", "player"]
+        );
+        assert!(app.set_state(0u8).is_err());
+    }
+}
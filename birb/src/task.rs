@@ -0,0 +1,317 @@
+use parking_lot::Mutex;
+use std::{
+    any::Any,
+    future::Future,
+    num::NonZero,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{mpsc, Arc},
+    task::{Context, Poll, Wake, Waker},
+    thread,
+};
+
+use crate::{schedule::Stage, Error};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Background threads which run tasks, kept apart from the rayon pool modules tick on so that
+/// long-running work never holds up a tick
+pub(crate) struct TaskPool {
+    sender: mpsc::Sender<Job>,
+}
+
+impl Default for TaskPool {
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, NonZero::get))
+    }
+}
+
+impl TaskPool {
+    /// # Panics
+    /// Panics if `threads` is zero or a thread cannot be spawned
+    pub fn new(threads: usize) -> Self {
+        assert!(threads > 0, "the task pool needs at least one thread");
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..threads {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("birb-task-{i}"))
+                .spawn(move || loop {
+                    // The lock is released before running the job so other workers can take
+                    // the next one. Workers exit once the pool is dropped and the queue drained.
+                    let job = receiver.lock().recv();
+                    let Ok(job) = job else {
+                        break;
+                    };
+                    // A panicking job must not take the worker down with it, or the tasks
+                    // queued behind it would never run
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                })
+                .expect("failed to spawn a task thread");
+        }
+        Self { sender }
+    }
+
+    pub fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
+        let task = Arc::new(FutureTask {
+            future: Mutex::new(Some(Box::pin(future))),
+            sender: self.sender.clone(),
+        });
+        task.schedule();
+    }
+}
+
+/// A future being driven by the pool, which queues itself to be polled again when woken
+struct FutureTask {
+    future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+    sender: mpsc::Sender<Job>,
+}
+
+impl FutureTask {
+    fn schedule(self: Arc<Self>) {
+        let sender = self.sender.clone();
+        // Fails only once the pool is gone, at which point nobody is waiting for the result
+        let _ = sender.send(Box::new(move || self.poll()));
+    }
+
+    fn poll(self: Arc<Self>) {
+        let mut slot = self.future.lock();
+        if let Some(future) = slot.as_mut() {
+            let waker = Waker::from(self.clone());
+            if future
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_ready()
+            {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl Wake for FutureTask {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+}
+
+/// Resolves to the panic's payload instead if polling the future panics
+pub(crate) struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F> CatchUnwind<F> {
+    pub fn new(future: F) -> Self {
+        Self(Box::pin(future))
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.0.as_mut().poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+enum State<T> {
+    Running,
+    /// Finished or panicked on the pool, but not yet handed to the app
+    Finished(Result<T, Error>),
+    Delivered(Result<T, Error>),
+    Taken,
+}
+
+/// The result slot shared between a [`Task`] and the pool running it
+pub(crate) struct Shared<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Shared<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(State::Running),
+        }
+    }
+
+    pub fn finish(&self, result: thread::Result<T>) {
+        let result = result.map_err(|payload| Error::TaskPanicked(panic_message(&*payload)));
+        *self.state.lock() = State::Finished(result);
+    }
+}
+
+/// The message a panic was raised with, if it was given one
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// A task whose result the app makes visible at the start of a stage
+pub(crate) trait Deliver: Send + Sync {
+    /// Makes the result visible to the task's handle if it has finished, returning whether
+    /// the task is done with
+    fn deliver(&self) -> bool;
+}
+
+impl<T: Send> Deliver for Shared<T> {
+    fn deliver(&self) -> bool {
+        let mut state = self.state.lock();
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Running => {
+                *state = State::Running;
+                false
+            }
+            State::Finished(value) | State::Delivered(value) => {
+                *state = State::Delivered(value);
+                true
+            }
+            State::Taken => true,
+        }
+    }
+}
+
+/// When a task's result is delivered, converted from a [`Stage`] to deliver it at the start of
+/// that stage on the first tick after the task finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delivery {
+    stage: Stage,
+    tick: u64,
+}
+
+impl Delivery {
+    /// Delivers at the start of `stage`, but not before the tick during which
+    /// [`App::ticks`](crate::App::ticks) is `tick`
+    #[must_use]
+    pub const fn on_tick(tick: u64, stage: Stage) -> Self {
+        Self { stage, tick }
+    }
+
+    pub(crate) fn is_due(&self, tick: u64, stage: Stage) -> bool {
+        self.stage == stage && tick >= self.tick
+    }
+}
+
+impl From<Stage> for Delivery {
+    fn from(stage: Stage) -> Self {
+        Self::on_tick(0, stage)
+    }
+}
+
+/// A handle to work spawned with [`App::spawn_task`](crate::App::spawn_task) or
+/// [`App::compute`](crate::App::compute).
+///
+/// Dropping the handle detaches the task, which keeps running but whose result is discarded.
+pub struct Task<T> {
+    pub(crate) shared: Arc<Shared<T>>,
+}
+
+impl<T> Task<T> {
+    /// Takes the result once it has been delivered, returning `None` before then and once it
+    /// has been taken
+    ///
+    /// # Errors
+    /// Returns [`Error::TaskPanicked`] if the work panicked
+    pub fn poll(&mut self) -> Option<Result<T, Error>> {
+        let mut state = self.shared.state.lock();
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Delivered(value) => Some(value),
+            other => {
+                *state = other;
+                None
+            }
+        }
+    }
+
+    /// Whether the work has completed on the pool, even if the result has not yet been
+    /// delivered
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        !matches!(*self.shared.state.lock(), State::Running)
+    }
+}
+
+impl<T> std::fmt::Debug for Task<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Task")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+        thread,
+        time::Duration,
+    };
+
+    use super::Delivery;
+    use crate::{schedule::Stage, App, Error};
+
+    /// Returns pending once, waking itself so that it is polled again
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_tasks() {
+        let mut app = App::new();
+        app.set_task_threads(2);
+        let mut computed = app.compute(Stage::Update, || 2 + 2);
+        let mut spawned = app.spawn_task(Stage::PostUpdate, async {
+            YieldOnce(false).await;
+            "done"
+        });
+        let mut later = app.compute(Delivery::on_tick(1, Stage::PreUpdate), || 1);
+        while !computed.is_finished() || !spawned.is_finished() || !later.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(computed.poll().is_none());
+
+        app.tick();
+        assert_eq!(computed.poll().map(Result::unwrap), Some(4));
+        assert!(computed.poll().is_none());
+        assert_eq!(spawned.poll().map(Result::unwrap), Some("done"));
+        assert!(later.poll().is_none());
+        app.tick();
+        assert_eq!(later.poll().map(Result::unwrap), Some(1));
+    }
+
+    #[test]
+    fn test_panicked_task() {
+        let mut app = App::new();
+        app.set_task_threads(1);
+        let mut panicked = app.compute(Stage::Update, || -> u32 { panic!("out of cheese") });
+        let mut later = app.compute(Stage::Update, || 1);
+        while !later.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        app.tick();
+        assert!(matches!(
+            panicked.poll(),
+            Some(Err(Error::TaskPanicked(message))) if message == "out of cheese"
+        ));
+        assert_eq!(later.poll().map(Result::unwrap), Some(1));
+        assert!(app.tasks.lock().is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{Duration, Instant};
+
+/// Tracks how often modules in [`Stage::FixedUpdate`](crate::schedule::Stage::FixedUpdate) run.
+///
+/// Each tick adds the time since the previous tick to an accumulator, then runs the fixed stage
+/// once for every whole step in it. Whatever is left over is exposed as [`FixedTime::alpha`] so
+/// that rendering can interpolate between the last two fixed steps.
+#[derive(Debug, Clone)]
+pub struct FixedTime {
+    step: Duration,
+    max_steps: u32,
+    accumulated: Duration,
+    steps: u32,
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self {
+            step: Duration::from_nanos(1_000_000_000 / 60),
+            max_steps: 8,
+            accumulated: Duration::ZERO,
+            steps: 0,
+        }
+    }
+}
+
+impl FixedTime {
+    /// The simulated time which passes during each fixed step, 1/60th of a second by default
+    #[must_use]
+    pub const fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// How far between the last fixed step and the next the current tick is, from 0 to 1
+    #[must_use]
+    pub fn alpha(&self) -> f32 {
+        self.accumulated.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// The number of fixed steps run during the current tick
+    #[must_use]
+    pub const fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// The most fixed steps run during one tick. Time beyond this is dropped, so that a tick
+    /// which runs long does not make the next one run even longer.
+    #[must_use]
+    pub const fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// # Panics
+    /// Panics if `step` is zero
+    pub fn set_step(&mut self, step: Duration) {
+        assert!(
+            !step.is_zero(),
+            "the fixed timestep must be greater than zero"
+        );
+        self.step = step;
+    }
+
+    pub const fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    /// Adds `delta` to the accumulator, returning how many fixed steps to run
+    pub(crate) fn accumulate(&mut self, delta: Duration) -> u32 {
+        self.accumulated += delta;
+        self.steps = 0;
+        while self.accumulated >= self.step {
+            if self.steps == self.max_steps {
+                self.accumulated = Duration::ZERO;
+                break;
+            }
+            self.accumulated -= self.step;
+            self.steps += 1;
+        }
+        self.steps
+    }
+}
+
+/// How [`MainThreadApp::run`](crate::MainThreadApp::run) paces ticks
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RunMode {
+    /// Ticks as fast as possible
+    #[default]
+    Unlimited,
+    /// Sleeps between ticks so that at most one starts per interval
+    Limited(Duration),
+    /// Ticks as fast as possible, but advances time by the given amount each tick rather than
+    /// measuring it, see [`MainThreadApp::advance`](crate::MainThreadApp::advance)
+    Fixed(Duration),
+}
+
+impl RunMode {
+    /// Limits the app to `rate` ticks per second
+    ///
+    /// # Panics
+    /// Panics if `rate` is zero
+    #[must_use]
+    pub fn per_second(rate: u32) -> Self {
+        assert!(rate > 0, "the tick rate must be greater than zero");
+        Self::Limited(Duration::from_secs(1) / rate)
+    }
+
+    /// Sleeps until the next tick is due. `next` is when the next tick should start, it is
+    /// reset if the app has fallen behind so that it does not try to catch up
+    pub(crate) fn wait(self, next: &mut Instant) {
+        let Self::Limited(interval) = self else {
+            return;
+        };
+        *next += interval;
+        let now = Instant::now();
+        if *next > now {
+            std::thread::sleep(*next - now);
+        } else {
+            *next = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RunMode;
+    use crate::{
+        schedule::{Schedule, Stage},
+        App, Module,
+    };
+
+    #[derive(Debug, Default)]
+    struct Counter(u32);
+
+    impl Module for Counter {
+        fn tick(&mut self, _: &App) {
+            self.0 += 1;
+        }
+
+        fn schedule(&self) -> Schedule {
+            Schedule::new().in_stage(Stage::FixedUpdate)
+        }
+    }
+
+    #[test]
+    fn test_fixed_update() {
+        let mut app = App::new();
+        app.register_module(Counter::default()).unwrap();
+        let step = app.fixed_time().step();
+
+        app.advance(step / 2);
+        assert_eq!(app.get_module::<Counter>().unwrap().0, 0);
+        assert!((app.fixed_time().alpha() - 0.5).abs() < 0.01);
+
+        app.advance(step * 3);
+        assert_eq!(app.get_module::<Counter>().unwrap().0, 3);
+        assert_eq!(app.fixed_time().steps(), 3);
+
+        app.advance(Duration::from_secs(10));
+        assert_eq!(app.get_module::<Counter>().unwrap().0, 11);
+        assert!(app.fixed_time().alpha().abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_run_for() {
+        let mut app = App::new();
+        app.register_module(Counter::default()).unwrap();
+        let step = app.fixed_time().step();
+        app.set_run_mode(RunMode::Fixed(step));
+        app.set_stepping(true);
+
+        app.run_for_ticks(5);
+        assert_eq!(app.ticks(), 5);
+        app.run_for(step * 3);
+        assert_eq!(app.ticks(), 8);
+        app.run_until(|app| app.get_module::<Counter>().unwrap().0 == 10);
+        assert_eq!(app.ticks(), 10);
+        assert_eq!(app.elapsed(), step * 10);
+    }
+}
@@ -1,15 +1,18 @@
 use rayon::prelude::*;
-use std::time::Instant;
 
-use birb::{App, Module};
+use birb::{
+    profile::Profiler,
+    schedule::{Schedule, Stage},
+    timestep::RunMode,
+    App, Module,
+};
 use birb_maths::two::*;
-use birb_utils::time::Clock;
+
+#[derive(Copy, Clone, Debug)]
+struct Position(Vector<f32>);
 
 #[derive(Copy, Clone)]
-struct Birb {
-    position: Vector<f32>,
-    velocity: Vector<f32>,
-}
+struct Velocity(Vector<f32>);
 
 #[derive(Debug)]
 struct BirbSystem {}
@@ -17,15 +20,23 @@ struct BirbSystem {}
 impl Module for BirbSystem {
     fn tick(&mut self, app: &App) {
         let gravity = app.get_module::<Gravity>().unwrap().gravity;
-        let delta = app.get_module::<Clock>().unwrap().delta();
-        app.get_entity_mut::<Birb>()
-            .unwrap()
-            .par_iter_mut()
-            .for_each(|birb| {
-                birb.position += birb.velocity * delta.as_secs_f32();
-                birb.velocity += gravity * delta.as_secs_f32();
+        let delta = app.fixed_time().step();
+        app.query::<(&mut Position, &mut Velocity), ()>()
+            .par_iter()
+            .for_each(|(position, velocity)| {
+                position.0 += velocity.0 * delta.as_secs_f32();
+                velocity.0 += gravity * delta.as_secs_f32();
             })
     }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::new()
+            .in_stage(Stage::FixedUpdate)
+            .after::<Gravity>()
+            .reads_module::<Gravity>()
+            .writes::<Position>()
+            .writes::<Velocity>()
+    }
 }
 
 #[derive(Debug)]
@@ -35,39 +46,49 @@ struct Gravity {
 
 impl Module for Gravity {
     fn tick(&mut self, _: &App) {}
+
+    fn schedule(&self) -> Schedule {
+        Schedule::new().in_stage(Stage::FixedUpdate)
+    }
 }
 
 pub fn main() {
     let mut app = App::new();
-    app.register_module(BirbSystem {});
+    app.register_module(BirbSystem {}).unwrap();
     app.register_module(Gravity {
         gravity: Vector::new(0.0, -9.81),
-    });
-    app.register(Clock::register);
+    })
+    .unwrap();
+    app.register_module(Profiler::new()).unwrap();
 
-    let birbs = vec![
-        Birb {
-            position: Vector::fill(0.0),
-            velocity: Vector::fill(0.0)
-        };
-        1_000_000
-    ];
-    app.register_entities(&birbs);
+    app.spawn_batch(
+        (0..1_000_000).map(|_| (Position(Vector::fill(0.0)), Velocity(Vector::fill(0.0)))),
+    );
 
-    let start = Instant::now();
-    for _ in 0..60 {
-        app.tick()
-    }
-    let end = Instant::now();
+    let step = app.fixed_time().step();
+    app.set_run_mode(RunMode::Fixed(step));
+    app.run_for_ticks(60);
 
     println!(
         "Fell to: {:?}",
-        app.get_entity::<Birb>().unwrap().first().unwrap().position
+        app.get_entity::<Position>()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap()
+            .0
     );
 
+    let profiler = app.get_module::<Profiler>().unwrap();
+    let frame = profiler.frame().unwrap();
     println!(
-        "1 tick took: {}ns {:.5}s",
-        (end - start).as_nanos() / 60,
-        (end - start).as_secs_f64() / 60.0
+        "1 tick took: avg {:?}, p99 {:?}, max {:?}",
+        frame.avg, frame.p99, frame.max
     );
+    for (module, stats) in profiler.modules() {
+        println!(
+            "  {module}: avg {:?}, p99 {:?}, waited {:?}",
+            stats.tick.avg, stats.tick.p99, stats.lock_wait.avg
+        );
+    }
 }
@@ -1,14 +1,18 @@
-use birb::{App, Module};
-use birb_window::{Key, Window};
-use birb_winit::WinitWindow;
+use birb::{timestep::RunMode, App, Module};
+use birb_window::{Event, Key, WindowPlugin};
+use birb_winit::WinitPlugin;
 
 #[derive(Debug)]
 struct CloseOnEscape {}
 
 impl Module for CloseOnEscape {
     fn tick(&mut self, app: &App) {
-        let window = app.get_module::<Window>().unwrap();
-        if window.is_down(Key::Escape) || window.is_down(Key::Q) {
+        let escaped = app
+            .events::<Event>()
+            .unwrap()
+            .iter()
+            .any(|event| matches!(event, Event::KeyPress(Key::Escape | Key::Q)));
+        if escaped {
             println!("Exitting");
             app.exit()
         }
@@ -17,7 +21,9 @@ impl Module for CloseOnEscape {
 
 pub fn main() {
     let mut app = App::new();
-    app.register(WinitWindow::register);
-    app.register_module(CloseOnEscape {});
+    app.add_plugin(WindowPlugin).unwrap();
+    app.add_plugin(WinitPlugin).unwrap();
+    app.register_module(CloseOnEscape {}).unwrap();
+    app.set_run_mode(RunMode::per_second(60));
     app.run();
 }
@@ -30,7 +30,7 @@ pub fn test_listener2(_log_entry: &birb_log::LogEntry) {
 
 pub fn main() {
     let mut app = App::new();
-    app.register_module(birb_log::Log::new());
-    app.register_module(LogExample {});
+    app.register_module(birb_log::Log::new()).unwrap();
+    app.register_module(LogExample {}).unwrap();
     app.run();
 }
@@ -1,4 +1,4 @@
-use birb::Module;
+use birb::{plugin::Plugin, Error, MainThreadApp, Module};
 use std::time::SystemTime;
 
 pub enum LogCategory {
@@ -42,9 +42,17 @@ impl Log {
     }
 
     pub fn deregister_listener(&mut self, func: Listener) {
-        self.listeners.retain(|x| *x != func);
+        self.listeners.retain(|x| !std::ptr::fn_addr_eq(*x, func));
     }
     pub fn info<T>(&self, object: &T, message: String) {
+        self.log(object, LogCategory::INFO, message);
+    }
+
+    pub fn error<T>(&self, object: &T, message: String) {
+        self.log(object, LogCategory::ERROR, message);
+    }
+
+    fn log<T>(&self, object: &T, category: LogCategory, message: String) {
         let ts: u64 = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .expect("SystemTime before unix epoch!")
@@ -54,10 +62,20 @@ impl Log {
             module: module_name,
             msg: message,
             timestamp: ts,
-            category: LogCategory::INFO,
+            category,
         };
         self.notify_listeners(&log_entry);
     }
 }
 
 impl Module for Log {}
+
+/// Registers the [`Log`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogPlugin;
+
+impl Plugin for LogPlugin {
+    fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+        app.register_module(Log::new())
+    }
+}
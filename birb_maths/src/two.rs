@@ -127,6 +127,29 @@ impl<T: Neg<Output = T> + Copy> Rotor<T> {
     }
 }
 
+impl Rotor<f32> {
+    /// The rotor which rotates anticlockwise by `angle` radians
+    #[must_use]
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            real: cos,
+            imaginary: sin,
+        }
+    }
+}
+
+impl<T: Mul<Output = T> + Add<Output = T> + Sub<Output = T> + Copy> Mul for Rotor<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            real: (self.real * rhs.real - self.imaginary * rhs.imaginary),
+            imaginary: (self.real * rhs.imaginary + self.imaginary * rhs.real),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RowMat<T> {
     pub x: Vector<T>,
@@ -214,6 +237,7 @@ mod tests {
         assert_eq!(b, a * i);
         let c = Vector::fill(1.0);
         assert_eq!(c * i, Vector::new(-1.0, 1.0));
+        assert_eq!(c * (i * i), c * i * i);
     }
 
     #[test]
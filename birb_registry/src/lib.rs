@@ -1,12 +1,17 @@
-use birb::Module;
+pub mod prefab;
+pub mod snapshot;
+
+use birb::{App, Error, Module};
+use birb_log::Log;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{collections::HashMap, fs::File, io::prelude::*};
 
 #[derive(Default, Debug)]
 pub struct Registry {
     data: HashMap<String, Vec<u8>>,
+    autosave: Option<PathBuf>,
 }
 
 // Implement Registry Methods
@@ -14,63 +19,153 @@ impl Registry {
     pub fn new() -> Self {
         Registry {
             data: HashMap::default(),
+            autosave: None,
+        }
+    }
+    /// Loads `path` when the module is initialised and saves back to it when the app shuts down
+    pub fn with_autosave<T: AsRef<Path>>(path: T) -> Self {
+        Registry {
+            data: HashMap::default(),
+            autosave: Some(path.as_ref().to_path_buf()),
         }
     }
-    pub fn store<T>(&mut self, key: String, object: &T)
+    /// # Errors
+    /// Returns an error if `object` cannot be serialized
+    pub fn store<T>(&mut self, key: String, object: &T) -> Result<(), Error>
     where
         T: Serialize,
     {
-        self.data
-            .insert(key, serde_json::to_string(object).unwrap().into_bytes());
+        let data = serde_json::to_vec(object).map_err(Error::serialization)?;
+        self.data.insert(key, data);
+        Ok(())
     }
-    pub fn get<T>(&self, key: String) -> T
+    /// # Errors
+    /// Returns an error if nothing is stored under `key` or if the stored value is not a `T`
+    pub fn get<T>(&self, key: String) -> Result<T, Error>
     where
         T: for<'a> Deserialize<'a>,
     {
-        return serde_json::from_slice(self.data.get(&key).unwrap()).unwrap();
+        let data = self.data.get(&key).ok_or(Error::MissingKey(key))?;
+        serde_json::from_slice(data).map_err(Error::serialization)
     }
-    pub fn load<T: AsRef<Path>>(&mut self, path: T) {
+    /// Replaces the stored data with the contents of `path`, doing nothing if it does not exist
+    ///
+    /// # Errors
+    /// Returns an error if `path` cannot be read or does not contain a saved registry
+    pub fn load<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
         let path = path.as_ref();
         if path.exists() {
-            let mut file = File::open(path).unwrap();
+            let mut file = File::open(path)?;
             let mut data = String::new();
-            file.read_to_string(&mut data).unwrap();
-            self.data = serde_json::from_str(&data).unwrap();
+            file.read_to_string(&mut data)?;
+            self.data = serde_json::from_str(&data).map_err(Error::serialization)?;
         }
+        Ok(())
     }
 
-    fn create_save_file<T: AsRef<Path>>(&mut self, path: T) {
-        File::create(path.as_ref())
-            .unwrap()
-            .write_all(serde_json::to_vec(&self.data).unwrap().as_slice())
-            .unwrap();
+    /// # Errors
+    /// Returns an error if `path` cannot be written
+    pub fn save<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let data = serde_json::to_vec(&self.data).map_err(Error::serialization)?;
+        File::create(path.as_ref())?.write_all(&data)?;
+        Ok(())
     }
+}
 
-    pub fn save<T: AsRef<Path>>(&mut self, path: T) {
-        let path = path.as_ref();
-        if path.exists() {
-            File::open(path)
-                .unwrap()
-                .write_all(serde_json::to_vec(&self.data).unwrap().as_slice())
-                .unwrap();
-        } else {
-            self.create_save_file(path)
+impl Registry {
+    /// Reports an autosave failure to the [`Log`], if one is registered
+    fn report(&self, app: &App, message: String) {
+        if let Ok(log) = app.get_module::<Log>() {
+            log.error(self, message);
         }
     }
 }
 
-impl Module for Registry {}
+impl Module for Registry {
+    fn init(&mut self, app: &mut App) {
+        if let Some(path) = self.autosave.clone() {
+            if let Err(err) = self.load(&path) {
+                self.report(
+                    app,
+                    format!("failed to load registry from {}: {err}", path.display()),
+                );
+            }
+        }
+    }
+
+    fn shutdown(&mut self, app: &App) {
+        if let Some(path) = self.autosave.clone() {
+            if let Err(err) = self.save(&path) {
+                self.report(
+                    app,
+                    format!("failed to save registry to {}: {err}", path.display()),
+                );
+            }
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use birb_log::{LogCategory, LogPlugin};
+    use std::sync::atomic::{AtomicBool, Ordering};
     #[test]
     fn test_registry_kv() {
         let mut registry = Registry::new();
         const INDEX_VALUE: &str = "test_value";
         const INDEX_KEY: &str = "TEST/key";
-        registry.store::<String>(INDEX_KEY.to_string(), &INDEX_VALUE.to_string());
-        let return_value = registry.get::<String>(INDEX_KEY.to_string());
+        registry
+            .store::<String>(INDEX_KEY.to_string(), &INDEX_VALUE.to_string())
+            .unwrap();
+        let return_value = registry.get::<String>(INDEX_KEY.to_string()).unwrap();
         assert_eq!(&return_value, &INDEX_VALUE);
+        assert!(matches!(
+            registry.get::<String>("missing".to_string()),
+            Err(Error::MissingKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_autosave() {
+        let path = std::env::temp_dir().join("birb_registry_test_autosave.json");
+        let _ = std::fs::remove_file(&path);
+
+        let mut app = App::new();
+        app.register_module(Registry::with_autosave(&path)).unwrap();
+        app.tick();
+        app.get_module_mut::<Registry>()
+            .unwrap()
+            .store("key".to_string(), &1u32)
+            .unwrap();
+        app.shutdown();
+
+        let mut app = App::new();
+        app.register_module(Registry::with_autosave(&path)).unwrap();
+        app.tick();
+        assert_eq!(
+            app.get_module::<Registry>()
+                .unwrap()
+                .get::<u32>("key".to_string())
+                .unwrap(),
+            1
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        static FAILED: AtomicBool = AtomicBool::new(false);
+        let mut app = App::new();
+        app.add_plugin(LogPlugin).unwrap();
+        app.get_module_mut::<Log>()
+            .unwrap()
+            .register_listener(|entry| {
+                if matches!(entry.category, LogCategory::ERROR) {
+                    FAILED.store(true, Ordering::Relaxed);
+                }
+            });
+        // The directory exists, but cannot be read as a registry
+        app.register_module(Registry::with_autosave(std::env::temp_dir()))
+            .unwrap();
+        app.tick();
+        assert!(FAILED.load(Ordering::Relaxed));
     }
 }
@@ -0,0 +1,217 @@
+use birb::{component::Bundle, entity::Entity, App, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs::File, io::prelude::*};
+
+use crate::snapshot::TypeRegistry;
+
+/// A template of components with default values, keyed by the names they are registered under
+/// in a [`TypeRegistry`]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
+    #[serde(default)]
+    components: BTreeMap<String, Value>,
+}
+
+impl Prefab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from the components of the prefab named `parent`, overriding them with this one's
+    #[must_use]
+    pub fn extends(mut self, parent: impl Into<String>) -> Self {
+        self.extends = Some(parent.into());
+        self
+    }
+
+    /// Adds the component registered under `name`, replacing any inherited value
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.components.insert(name.into(), value);
+        self
+    }
+}
+
+/// Named prefabs, usually loaded from a data file mapping each name to its [`Prefab`]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Prefabs {
+    prefabs: BTreeMap<String, Prefab>,
+}
+
+impl Prefabs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a prefab, returning the one it replaced
+    pub fn insert(&mut self, name: impl Into<String>, prefab: Prefab) -> Option<Prefab> {
+        self.prefabs.insert(name.into(), prefab)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Every component of the prefab, including inherited ones
+    fn resolve(&self, name: &str) -> Result<BTreeMap<&str, &Value>, Error> {
+        let mut chain: Vec<(&str, &Prefab)> = Vec::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if chain.iter().any(|&(seen, _)| seen == name) {
+                return Err(Error::PrefabCycle(name.to_string()));
+            }
+            let prefab = self
+                .prefabs
+                .get(name)
+                .ok_or_else(|| Error::MissingKey(name.to_string()))?;
+            chain.push((name, prefab));
+            next = prefab.extends.as_deref();
+        }
+        let mut components = BTreeMap::new();
+        for (_, prefab) in chain.iter().rev() {
+            for (component, value) in &prefab.components {
+                components.insert(component.as_str(), value);
+            }
+        }
+        Ok(components)
+    }
+
+    /// Spawns an entity from the prefab named `name`
+    ///
+    /// # Errors
+    /// See [`Prefabs::spawn_batch`]
+    pub fn spawn(
+        &self,
+        app: &mut App,
+        registry: &TypeRegistry,
+        name: &str,
+    ) -> Result<Entity, Error> {
+        self.spawn_with(app, registry, name, ())
+    }
+
+    /// Spawns an entity from the prefab named `name`, with the components in `bundle` in place
+    /// of the prefab's
+    ///
+    /// # Errors
+    /// See [`Prefabs::spawn_batch`]
+    pub fn spawn_with<B: Bundle>(
+        &self,
+        app: &mut App,
+        registry: &TypeRegistry,
+        name: &str,
+        bundle: B,
+    ) -> Result<Entity, Error> {
+        let entities = self.spawn_batch(app, registry, name, [bundle])?;
+        Ok(entities[0])
+    }
+
+    /// Spawns an entity from the prefab named `name` for each bundle, whose components take
+    /// the place of the prefab's, returning their handles in order. The prefab's components are
+    /// deserialized once and cloned into every entity, which is spawned with all of its
+    /// components at once.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingKey`] if the prefab, one it extends or one of its components is
+    /// not known, [`Error::PrefabCycle`] if a prefab extends itself, or an error if a component
+    /// fails to deserialize. Nothing is spawned on error.
+    ///
+    /// # Panics
+    /// Panics if the bundle contains the same component twice
+    pub fn spawn_batch<B: Bundle>(
+        &self,
+        app: &mut App,
+        registry: &TypeRegistry,
+        name: &str,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Result<Vec<Entity>, Error> {
+        let components = self
+            .resolve(name)?
+            .into_iter()
+            .map(|(component, value)| registry.deserialize_component(component, value))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(app.spawn_batch_with(&components, bundles))
+    }
+
+    /// # Errors
+    /// Returns an error if `path` cannot be written
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<(), Error> {
+        let data = serde_json::to_vec_pretty(self).map_err(Error::serialization)?;
+        File::create(path.as_ref())?.write_all(&data)?;
+        Ok(())
+    }
+
+    /// # Errors
+    /// Returns an error if `path` cannot be read or does not contain prefabs
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let mut data = String::new();
+        File::open(path.as_ref())?.read_to_string(&mut data)?;
+        serde_json::from_str(&data).map_err(Error::serialization)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Position(i32, i32);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Name(String);
+
+    #[test]
+    fn test_prefabs() {
+        let registry = TypeRegistry::new()
+            .component::<Position>("position")
+            .component::<Health>("health")
+            .component::<Name>("name");
+        let path = std::env::temp_dir().join("birb_registry_test_prefabs.json");
+        let data = json!({
+            "enemy": { "components": { "position": [0, 0], "health": 10 } },
+            "goblin": { "extends": "enemy", "components": { "name": "goblin", "health": 5 } },
+            "loop": { "extends": "loop" },
+        });
+        std::fs::write(&path, data.to_string()).unwrap();
+        let prefabs = Prefabs::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut app = App::new();
+        let enemy = prefabs.spawn(&mut app, &registry, "enemy").unwrap();
+        assert_eq!(*app.get::<Health>(enemy).unwrap(), Health(10));
+        assert!(app.get::<Name>(enemy).is_err());
+
+        let goblins = prefabs
+            .spawn_batch(
+                &mut app,
+                &registry,
+                "goblin",
+                (0..1000).map(|x| (Position(x, 1),)),
+            )
+            .unwrap();
+        assert_eq!(goblins.len(), 1000);
+        let last = goblins[999];
+        assert_eq!(*app.get::<Position>(last).unwrap(), Position(999, 1));
+        assert_eq!(*app.get::<Health>(last).unwrap(), Health(5));
+        assert_eq!(*app.get::<Name>(last).unwrap(), Name("goblin".to_string()));
+
+        assert!(matches!(
+            prefabs.spawn(&mut app, &registry, "orc"),
+            Err(Error::MissingKey(name)) if name == "orc"
+        ));
+        assert!(matches!(
+            prefabs.spawn(&mut app, &registry, "loop"),
+            Err(Error::PrefabCycle(name)) if name == "loop"
+        ));
+        assert_eq!(app.query::<Entity, ()>().iter().count(), 1001);
+    }
+}
@@ -0,0 +1,320 @@
+use birb::{
+    component::{Component, DynamicComponent},
+    entity::Entity,
+    App, Error, Module,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs::File, io::prelude::*};
+
+/// An entity handle as its index and generation
+type SavedEntity = (u32, u32);
+
+/// A component along with the entity it is attached to
+type SavedComponent = (SavedEntity, Value);
+
+type ApplyComponents = Box<dyn FnOnce(&mut App) -> Result<(), Error>>;
+type ApplyModule = Box<dyn FnOnce(&App) -> Result<(), Error>>;
+
+#[derive(Debug, Clone, Copy)]
+struct ComponentFns {
+    save: fn(&App) -> Result<Vec<SavedComponent>, Error>,
+    load: fn(&[SavedComponent]) -> Result<ApplyComponents, Error>,
+    dynamic: fn(&Value) -> Result<DynamicComponent, Error>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ModuleFns {
+    save: fn(&App) -> Result<Value, Error>,
+    load: fn(&App, &Value) -> Result<ApplyModule, Error>,
+}
+
+/// Maps stable names to the component and module types which are saved in a [`Snapshot`].
+///
+/// Names are what is written to disk, so they must not change between builds which need to
+/// read each other's snapshots. Types which are not registered are left out of snapshots.
+#[derive(Debug, Default, Clone)]
+pub struct TypeRegistry {
+    components: BTreeMap<&'static str, ComponentFns>,
+    modules: BTreeMap<&'static str, ModuleFns>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        TypeRegistry::default()
+    }
+
+    /// Saves every `T` attached to an entity under `name`, and lets prefabs use it. Prefabs
+    /// clone it into every entity spawned from them.
+    ///
+    /// # Panics
+    /// Panics if another component is already registered under `name`
+    #[must_use]
+    pub fn component<T>(mut self, name: &'static str) -> Self
+    where
+        T: Component + Clone + Serialize + DeserializeOwned,
+    {
+        let fns = ComponentFns {
+            save: save_component::<T>,
+            load: load_component::<T>,
+            dynamic: dynamic_component::<T>,
+        };
+        assert!(
+            self.components.insert(name, fns).is_none(),
+            "{name} is already registered"
+        );
+        self
+    }
+
+    /// Saves the module `T` under `name`. Restoring replaces the registered module's value, so
+    /// it must be registered in the app being restored.
+    ///
+    /// # Panics
+    /// Panics if another module is already registered under `name`
+    #[must_use]
+    pub fn module<T>(mut self, name: &'static str) -> Self
+    where
+        T: Module + Serialize + DeserializeOwned,
+    {
+        let fns = ModuleFns {
+            save: save_module::<T>,
+            load: load_module::<T>,
+        };
+        assert!(
+            self.modules.insert(name, fns).is_none(),
+            "{name} is already registered"
+        );
+        self
+    }
+
+    /// Deserializes the component registered under `name`
+    pub(crate) fn deserialize_component(
+        &self,
+        name: &str,
+        value: &Value,
+    ) -> Result<DynamicComponent, Error> {
+        let fns = self
+            .components
+            .get(name)
+            .ok_or_else(|| Error::MissingKey(name.to_string()))?;
+        (fns.dynamic)(value)
+    }
+
+    /// Captures every entity, along with its registered components, and every registered
+    /// module
+    ///
+    /// # Errors
+    /// Returns an error if a registered module is missing or anything fails to serialize
+    pub fn snapshot(&self, app: &App) -> Result<Snapshot, Error> {
+        let entities = app
+            .query::<Entity, ()>()
+            .iter()
+            .map(|entity| (entity.index(), entity.generation()))
+            .collect();
+        let components = self
+            .components
+            .iter()
+            .map(|(name, fns)| Ok((name.to_string(), (fns.save)(app)?)))
+            .collect::<Result<_, Error>>()?;
+        let modules = self
+            .modules
+            .iter()
+            .map(|(name, fns)| Ok((name.to_string(), (fns.save)(app)?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(Snapshot {
+            entities,
+            components,
+            modules,
+        })
+    }
+
+    /// Replaces every entity in `app` with those in `snapshot`, keeping their handles, and
+    /// overwrites every module it contains.
+    ///
+    /// Everything is deserialized, and every saved module looked up, before `app` is touched,
+    /// so those errors leave it unchanged. Errors while respawning entities or inserting their
+    /// components, such as a snapshot listing the same entity twice, are returned part way
+    /// through, after the old entities have been despawned.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingKey`] if the snapshot contains a type which is not registered,
+    /// [`Error::MissingModule`] if a saved module is not registered in `app`, or an error if
+    /// anything fails to deserialize
+    pub fn restore(&self, app: &mut App, snapshot: &Snapshot) -> Result<(), Error> {
+        let mut components = Vec::new();
+        for (name, saved) in &snapshot.components {
+            let fns = self
+                .components
+                .get(name.as_str())
+                .ok_or_else(|| Error::MissingKey(name.clone()))?;
+            components.push((fns.load)(saved)?);
+        }
+        let mut modules = Vec::new();
+        for (name, saved) in &snapshot.modules {
+            let fns = self
+                .modules
+                .get(name.as_str())
+                .ok_or_else(|| Error::MissingKey(name.clone()))?;
+            modules.push((fns.load)(app, saved)?);
+        }
+
+        let alive: Vec<Entity> = app.query::<Entity, ()>().iter().collect();
+        for entity in alive {
+            // Children are despawned along with their parent, so may already be gone
+            let _ = app.despawn(entity);
+        }
+        for &(index, generation) in &snapshot.entities {
+            app.spawn_at(Entity::from_raw(index, generation), ())?;
+        }
+        for apply in components {
+            apply(app)?;
+        }
+        for apply in modules {
+            apply(app)?;
+        }
+        Ok(())
+    }
+}
+
+fn save_component<T>(app: &App) -> Result<Vec<SavedComponent>, Error>
+where
+    T: Component + Serialize,
+{
+    app.query::<(Entity, &T), ()>()
+        .iter()
+        .map(|(entity, component)| {
+            let value = serde_json::to_value(component).map_err(Error::serialization)?;
+            Ok(((entity.index(), entity.generation()), value))
+        })
+        .collect()
+}
+
+fn load_component<T>(saved: &[SavedComponent]) -> Result<ApplyComponents, Error>
+where
+    T: Component + DeserializeOwned,
+{
+    let components = saved
+        .iter()
+        .map(|&((index, generation), ref value)| {
+            let component: T = T::deserialize(value).map_err(Error::serialization)?;
+            Ok((Entity::from_raw(index, generation), component))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Box::new(move |app| {
+        for (entity, component) in components {
+            app.insert(entity, (component,))?;
+        }
+        Ok(())
+    }))
+}
+
+fn dynamic_component<T>(value: &Value) -> Result<DynamicComponent, Error>
+where
+    T: Component + Clone + DeserializeOwned,
+{
+    let component = T::deserialize(value).map_err(Error::serialization)?;
+    Ok(DynamicComponent::new(component))
+}
+
+fn save_module<T>(app: &App) -> Result<Value, Error>
+where
+    T: Module + Serialize,
+{
+    serde_json::to_value(&*app.get_module::<T>()?).map_err(Error::serialization)
+}
+
+fn load_module<T>(app: &App, saved: &Value) -> Result<ApplyModule, Error>
+where
+    T: Module + DeserializeOwned,
+{
+    // Checked up front so that a missing module is reported before anything is restored
+    drop(app.get_module::<T>()?);
+    let module = T::deserialize(saved).map_err(Error::serialization)?;
+    Ok(Box::new(move |app| {
+        *app.get_module_mut::<T>()? = module;
+        Ok(())
+    }))
+}
+
+/// The saved state of an [`App`], made with [`TypeRegistry::snapshot`]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    entities: Vec<SavedEntity>,
+    components: BTreeMap<String, Vec<SavedComponent>>,
+    modules: BTreeMap<String, Value>,
+}
+
+impl Snapshot {
+    /// # Errors
+    /// Returns an error if `path` cannot be written
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<(), Error> {
+        let data = serde_json::to_vec(self).map_err(Error::serialization)?;
+        File::create(path.as_ref())?.write_all(&data)?;
+        Ok(())
+    }
+
+    /// # Errors
+    /// Returns an error if `path` cannot be read or does not contain a snapshot
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let mut data = String::new();
+        File::open(path.as_ref())?.read_to_string(&mut data)?;
+        serde_json::from_str(&data).map_err(Error::serialization)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Position(i32, i32);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Name(String);
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Score(u32);
+
+    impl Module for Score {}
+
+    #[test]
+    fn test_snapshot() {
+        let registry = TypeRegistry::new()
+            .component::<Position>("position")
+            .component::<Name>("name")
+            .module::<Score>("score");
+        let mut app = App::new();
+        app.register_module(Score(10)).unwrap();
+        let a = app.spawn((Position(1, 2), Name("a".to_string())));
+        let b = app.spawn((Position(3, 4),));
+        let c = app.spawn((5u8,));
+
+        let path = std::env::temp_dir().join("birb_registry_test_snapshot.json");
+        registry.snapshot(&app).unwrap().save(&path).unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        app.despawn(a).unwrap();
+        let d = app.spawn((Position(0, 0),));
+        app.get_module_mut::<Score>().unwrap().0 = 0;
+        registry.restore(&mut app, &snapshot).unwrap();
+
+        assert_eq!(*app.get::<Position>(a).unwrap(), Position(1, 2));
+        assert_eq!(*app.get::<Name>(a).unwrap(), Name("a".to_string()));
+        assert_eq!(*app.get::<Position>(b).unwrap(), Position(3, 4));
+        assert!(app.get::<Position>(d).is_err());
+        // Unregistered components are not saved, but the entity still is
+        assert!(app.get::<u8>(c).is_err());
+        assert!(app.despawn(c).is_ok());
+        assert_eq!(app.get_module::<Score>().unwrap().0, 10);
+
+        let unknown = TypeRegistry::new().component::<Position>("position");
+        assert!(matches!(
+            unknown.restore(&mut app, &snapshot),
+            Err(Error::MissingKey(name)) if name == "name"
+        ));
+        assert_eq!(*app.get::<Name>(a).unwrap(), Name("a".to_string()));
+    }
+}
@@ -0,0 +1,214 @@
+#![deny(clippy::all)]
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+
+use birb::{
+    entity::Entity,
+    hierarchy::{Children, Parent},
+    plugin::Plugin,
+    query::{With, Without},
+    schedule::{Schedule, Stage},
+    App, Error, MainThreadApp, Module,
+};
+use birb_maths::two::{Rotor, Vector};
+
+/// An entity's position and rotation relative to its parent, or to the world if it has none
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector<f32>,
+    pub rotation: Rotor<f32>,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vector { x: 0.0, y: 0.0 },
+        rotation: Rotor {
+            real: 1.0,
+            imaginary: 0.0,
+        },
+    };
+
+    #[must_use]
+    pub const fn from_translation(translation: Vector<f32>) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    #[must_use]
+    pub const fn with_rotation(mut self, rotation: Rotor<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// An entity's position and rotation in the world, computed from its [`Transform`] and those
+/// of its ancestors in [`Stage::PostUpdate`]. Entities need both components to be placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform {
+    pub translation: Vector<f32>,
+    pub rotation: Rotor<f32>,
+}
+
+impl GlobalTransform {
+    /// Places a transform relative to this one
+    #[must_use]
+    pub fn mul_transform(&self, local: Transform) -> Self {
+        Self {
+            translation: self.transform_point(local.translation),
+            rotation: self.rotation * local.rotation,
+        }
+    }
+
+    /// Moves a point from this transform's space into the world's
+    #[must_use]
+    pub fn transform_point(&self, point: Vector<f32>) -> Vector<f32> {
+        self.translation + point * self.rotation
+    }
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Transform::IDENTITY.into()
+    }
+}
+
+impl From<Transform> for GlobalTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+}
+
+/// Composes every [`Transform`] with those of its ancestors to update the
+/// [`GlobalTransform`]s
+///
+/// Only those whose value moves are written, so
+/// [`Changed<GlobalTransform>`](birb::query::Changed) matches just the entities which moved.
+#[derive(Debug, Default)]
+pub struct TransformPropagation;
+
+impl TransformPropagation {
+    /// Writes `global` to the entity's [`GlobalTransform`], if it has one which differs
+    fn place(app: &App, entity: Entity, global: GlobalTransform) {
+        if app
+            .get::<GlobalTransform>(entity)
+            .is_ok_and(|placed| *placed != global)
+        {
+            if let Ok(mut placed) = app.get_mut::<GlobalTransform>(entity) {
+                *placed = global;
+            }
+        }
+    }
+}
+
+impl Module for TransformPropagation {
+    fn tick(&mut self, app: &App) {
+        let mut stack: Vec<(Entity, GlobalTransform)> = app
+            .query::<(Entity, &Transform), (With<GlobalTransform>, Without<Parent>)>()
+            .iter()
+            .map(|(entity, local)| (entity, (*local).into()))
+            .collect();
+        for &(entity, global) in &stack {
+            Self::place(app, entity, global);
+        }
+
+        while let Some((entity, parent)) = stack.pop() {
+            let Ok(children) = app.get::<Children>(entity) else {
+                continue;
+            };
+            for &child in children.get() {
+                // Children without a transform are skipped, along with their descendants
+                let Ok(local) = app.get::<Transform>(child).map(|local| *local) else {
+                    continue;
+                };
+                let global = parent.mul_transform(local);
+                Self::place(app, child, global);
+                stack.push((child, global));
+            }
+        }
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::new()
+            .in_stage(Stage::PostUpdate)
+            .reads::<Transform>()
+            .reads::<Parent>()
+            .reads::<Children>()
+            .writes::<GlobalTransform>()
+    }
+}
+
+/// Registers [`TransformPropagation`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TransformPlugin;
+
+impl Plugin for TransformPlugin {
+    fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+        app.register_module(TransformPropagation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use birb::App;
+    use birb_maths::two::{Rotor, Vector};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::{GlobalTransform, Transform, TransformPlugin};
+
+    #[test]
+    fn test_propagation() {
+        let mut app = App::new();
+        app.add_plugin(TransformPlugin).unwrap();
+        let quarter = Rotor::new(0.0, 1.0);
+        let tank = app.spawn((
+            Transform::from_translation(Vector::new(10.0, 0.0)).with_rotation(quarter),
+            GlobalTransform::default(),
+        ));
+        let turret = app.spawn((
+            Transform::from_translation(Vector::new(2.0, 0.0)),
+            GlobalTransform::default(),
+        ));
+        let barrel = app.spawn((
+            Transform::from_translation(Vector::new(1.0, 0.0)).with_rotation(quarter),
+            GlobalTransform::default(),
+        ));
+        app.set_parent(turret, tank).unwrap();
+        app.set_parent(barrel, turret).unwrap();
+        app.tick();
+
+        let global = *app.get::<GlobalTransform>(barrel).unwrap();
+        assert_eq!(global.translation, Vector::new(10.0, 3.0));
+        assert_eq!(global.rotation, Rotor::new(-1.0, 0.0));
+        let global = *app.get::<GlobalTransform>(turret).unwrap();
+        assert_eq!(global.translation, Vector::new(10.0, 2.0));
+
+        // Only transforms which moved are written
+        let changed = Arc::new(AtomicUsize::new(0));
+        let count = changed.clone();
+        app.on_change::<GlobalTransform>(move |_, _| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        // The first run reports every transform placed so far
+        app.tick();
+        changed.store(0, Ordering::Relaxed);
+        app.tick();
+        assert_eq!(changed.load(Ordering::Relaxed), 0);
+        app.get_mut::<Transform>(barrel).unwrap().rotation = Rotor::new(1.0, 0.0);
+        app.tick();
+        assert_eq!(changed.load(Ordering::Relaxed), 1);
+    }
+}
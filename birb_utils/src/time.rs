@@ -1,37 +1,95 @@
 use std::time::{Duration, Instant};
 
-use birb::{App, Module};
+use birb::{
+    plugin::Plugin,
+    schedule::{Schedule, Stage},
+    App, Error, MainThreadApp, Module,
+};
 
+use crate::timer::{TimerFired, Timers};
+
+/// Game time, which advances by the app's delta each tick and can be paused or scaled
 #[derive(Debug)]
 pub struct Clock {
     start: Instant,
-    last_frame: Instant,
     delta: Duration,
+    paused: bool,
+    scale: f32,
 }
 
-impl Clock {
-    pub fn register(app: &mut App) {
-        let now = Instant::now();
-        app.register_module(Self {
-            start: now,
-            last_frame: now,
+/// Registers the [`Clock`] and [`Timers`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimePlugin;
+
+impl Plugin for TimePlugin {
+    fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+        app.register_module(Clock {
+            start: Instant::now(),
             delta: Duration::ZERO,
-        });
+            paused: false,
+            scale: 1.0,
+        })?;
+        app.register_module(Timers::new())?;
+        app.add_event::<TimerFired>();
+        Ok(())
     }
+}
 
+impl Clock {
+    /// How far game time moved this tick, zero while paused
     pub fn delta(&self) -> Duration {
+        if self.paused {
+            Duration::ZERO
+        } else {
+            self.delta.mul_f32(self.scale)
+        }
+    }
+
+    /// How long this tick was, ignoring pausing and scaling
+    pub fn raw_delta(&self) -> Duration {
         self.delta
     }
 
+    /// Wall time since the clock was registered
     pub fn elapsed(&self) -> Duration {
         Instant::now() - self.start
     }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Makes game time run `scale` times as fast as real time
+    ///
+    /// # Panics
+    /// Panics if `scale` is negative or not finite
+    pub fn set_scale(&mut self, scale: f32) {
+        assert!(
+            scale.is_finite() && scale >= 0.0,
+            "the time scale must be finite and non-negative"
+        );
+        self.scale = scale;
+    }
 }
 
 impl Module for Clock {
-    fn tick(&mut self, _: &App) {
-        let now = Instant::now();
-        self.delta = now - self.last_frame;
-        self.last_frame = now;
+    fn tick(&mut self, app: &App) {
+        self.delta = app.delta();
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::new().in_stage(Stage::PreUpdate)
     }
 }
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use std::time::Duration;
+
+use birb::{
+    schedule::{Schedule, Stage},
+    App, Module,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::time::Clock;
+
+/// Identifies a timer started on [`Timers`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TimerId(u64);
+
+/// Sent each time a timer expires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerFired(pub TimerId);
+
+type Callback = Arc<dyn Fn(&App) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Timer {
+    duration: Duration,
+    remaining: Duration,
+    repeating: bool,
+}
+
+/// One-shot and repeating timers, counted down by the [`Clock`]'s delta so that they stop while
+/// it is paused and follow its time scale.
+///
+/// Each expiry sends a [`TimerFired`] event and runs the timer's callbacks. Timers can be saved
+/// in a snapshot, but callbacks cannot, so they must be added again after restoring one.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Timers {
+    next: u64,
+    timers: BTreeMap<TimerId, Timer>,
+    #[serde(skip)]
+    callbacks: HashMap<TimerId, Vec<Callback>>,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a timer which fires once after `duration`
+    ///
+    /// # Panics
+    /// Panics if `duration` is zero
+    pub fn once(&mut self, duration: Duration) -> TimerId {
+        self.start(duration, false)
+    }
+
+    /// Starts a timer which fires every `interval`
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero
+    pub fn repeating(&mut self, interval: Duration) -> TimerId {
+        self.start(interval, true)
+    }
+
+    fn start(&mut self, duration: Duration, repeating: bool) -> TimerId {
+        assert!(!duration.is_zero(), "timers must last longer than zero");
+        let id = TimerId(self.next);
+        self.next += 1;
+        let timer = Timer {
+            duration,
+            remaining: duration,
+            repeating,
+        };
+        self.timers.insert(id, timer);
+        id
+    }
+
+    /// Runs `callback` each time the timer fires. Callbacks run while the timers are locked, so
+    /// they must use [`App::commands`] to start or cancel timers.
+    pub fn on_fire(&mut self, id: TimerId, callback: impl Fn(&App) + Send + Sync + 'static) {
+        self.callbacks
+            .entry(id)
+            .or_default()
+            .push(Arc::new(callback));
+    }
+
+    /// Stops a timer, returning `false` if it had already finished or been cancelled
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        self.callbacks.remove(&id);
+        self.timers.remove(&id).is_some()
+    }
+
+    /// How long until the timer next fires, or `None` if it has finished or been cancelled
+    pub fn remaining(&self, id: TimerId) -> Option<Duration> {
+        self.timers.get(&id).map(|timer| timer.remaining)
+    }
+}
+
+impl Debug for Timers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timers")
+            .field("timers", &self.timers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Module for Timers {
+    fn tick(&mut self, app: &App) {
+        let Ok(delta) = app.get_module::<Clock>().map(|clock| clock.delta()) else {
+            return;
+        };
+        let mut fired = Vec::new();
+        self.timers.retain(|id, timer| {
+            let mut elapsed = delta;
+            while elapsed >= timer.remaining {
+                fired.push(*id);
+                if !timer.repeating {
+                    return false;
+                }
+                elapsed -= timer.remaining;
+                timer.remaining = timer.duration;
+            }
+            timer.remaining -= elapsed;
+            true
+        });
+
+        for id in fired {
+            let _ = app.send(TimerFired(id));
+            for callback in self.callbacks.get(&id).into_iter().flatten() {
+                callback(app);
+            }
+            if !self.timers.contains_key(&id) {
+                self.callbacks.remove(&id);
+            }
+        }
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::new()
+            .in_stage(Stage::PreUpdate)
+            .after::<Clock>()
+            .reads_module::<Clock>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use birb::App;
+    use birb_registry::snapshot::TypeRegistry;
+
+    use super::{TimerFired, Timers};
+    use crate::time::{Clock, TimePlugin};
+
+    static CALLED: AtomicU32 = AtomicU32::new(0);
+
+    #[test]
+    fn test_timers() {
+        let mut app = App::new();
+        app.add_plugin(TimePlugin).unwrap();
+        let second = Duration::from_secs(1);
+        let (once, repeating) = {
+            let mut timers = app.get_module_mut::<Timers>().unwrap();
+            let once = timers.once(second * 3);
+            let repeating = timers.repeating(second);
+            timers.on_fire(repeating, |_| {
+                CALLED.fetch_add(1, Ordering::Relaxed);
+            });
+            (once, repeating)
+        };
+
+        app.advance(second * 2);
+        assert_eq!(CALLED.load(Ordering::Relaxed), 2);
+        app.get_module_mut::<Clock>().unwrap().set_scale(0.5);
+        app.advance(second * 2);
+        let fired: Vec<TimerFired> = app
+            .events::<TimerFired>()
+            .unwrap()
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(fired, [TimerFired(once), TimerFired(repeating)]);
+
+        app.get_module_mut::<Clock>().unwrap().pause();
+        app.advance(second * 10);
+        assert_eq!(CALLED.load(Ordering::Relaxed), 3);
+        let timers = app.get_module::<Timers>().unwrap();
+        assert_eq!(timers.remaining(once), None);
+        assert_eq!(timers.remaining(repeating), Some(second));
+    }
+
+    #[test]
+    fn test_timers_snapshot() {
+        let registry = TypeRegistry::new().module::<Timers>("timers");
+        let mut app = App::new();
+        app.add_plugin(TimePlugin).unwrap();
+        let second = Duration::from_secs(1);
+        let once = app.get_module_mut::<Timers>().unwrap().once(second * 3);
+        app.advance(second);
+        let snapshot = registry.snapshot(&app).unwrap();
+
+        app.advance(second);
+        assert_eq!(
+            app.get_module::<Timers>().unwrap().remaining(once),
+            Some(second)
+        );
+        registry.restore(&mut app, &snapshot).unwrap();
+        assert_eq!(
+            app.get_module::<Timers>().unwrap().remaining(once),
+            Some(second * 2)
+        );
+    }
+}
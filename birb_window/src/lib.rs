@@ -2,7 +2,7 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 
-use birb::Module;
+use birb::{plugin::Plugin, App, Error, MainThreadApp, Module};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Key {
@@ -173,11 +173,25 @@ impl Key {
 pub enum Event {
     KeyPress(Key),
     KeyRelease(Key),
+    /// The window gained (`true`) or lost (`false`) keyboard focus
+    Focus(bool),
 }
 
-#[derive(Debug, Default)]
+/// The keys held down and whether the window has focus, which it is assumed to have when
+/// opened until the backend says otherwise
+#[derive(Debug)]
 pub struct Window {
     down: Vec<Key>,
+    focused: bool,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            down: Vec::new(),
+            focused: true,
+        }
+    }
 }
 
 impl Window {
@@ -191,6 +205,11 @@ impl Window {
         self.down.contains(&key)
     }
 
+    #[must_use]
+    pub const fn has_focus(&self) -> bool {
+        self.focused
+    }
+
     pub fn submit(&mut self, event: Event) {
         println!("{event:?}");
         match event {
@@ -204,8 +223,52 @@ impl Window {
                     self.down.remove(index);
                 }
             }
+            Event::Focus(focused) => {
+                self.focused = focused;
+                if !focused {
+                    self.down.clear();
+                }
+            }
         }
     }
 }
 
 impl Module for Window {}
+
+/// Registers the [`Window`] module and the [`Event`] event, for a windowing backend to feed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WindowPlugin;
+
+impl Plugin for WindowPlugin {
+    fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+        app.register_module(Window::new())?;
+        app.add_event::<Event>();
+        Ok(())
+    }
+}
+
+/// Run condition which holds while the [`Window`] has focus, for use with
+/// [`Schedule::run_if`](birb::schedule::Schedule::run_if)
+#[must_use]
+pub fn has_focus(app: &App) -> bool {
+    app.get_module::<Window>()
+        .is_ok_and(|window| window.has_focus())
+}
+
+#[cfg(test)]
+mod tests {
+    use birb::App;
+
+    use super::{has_focus, Event, Window, WindowPlugin};
+
+    #[test]
+    fn test_focus() {
+        let mut app = App::new();
+        app.add_plugin(WindowPlugin).unwrap();
+        assert!(has_focus(&app));
+        app.get_module_mut::<Window>()
+            .unwrap()
+            .submit(Event::Focus(false));
+        assert!(!has_focus(&app));
+    }
+}
@@ -2,8 +2,11 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 
-use birb::{MainThreadApp, MainThreadModule};
-use birb_window::{Event, Key};
+use birb::{
+    plugin::{Plugin, PluginId},
+    Error, MainThreadApp, MainThreadModule,
+};
+use birb_window::{Event, Key, WindowPlugin};
 
 use winit::platform::run_on_demand::EventLoopExtRunOnDemand;
 
@@ -128,19 +131,27 @@ pub struct WinitWindow {
     window: winit::window::Window,
 }
 
-impl WinitWindow {
-    /// # Panics
-    /// Panics if window initialisation fails
-    pub fn register(app: &mut MainThreadApp) {
-        app.register_module(birb_window::Window::new());
+/// Opens a window with winit and feeds its input to the [`WindowPlugin`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WinitPlugin;
 
-        let event_loop = winit::event_loop::EventLoopBuilder::new().build().unwrap();
+impl Plugin for WinitPlugin {
+    fn requires(&self) -> Vec<PluginId> {
+        vec![PluginId::of::<WindowPlugin>()]
+    }
+
+    fn build(self, app: &mut MainThreadApp) -> Result<(), Error> {
+        let event_loop = winit::event_loop::EventLoopBuilder::new()
+            .build()
+            .map_err(|err| Error::plugin_failed(self.name(), err))?;
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
         let window = winit::window::WindowBuilder::new()
             .build(&event_loop)
-            .unwrap();
+            .map_err(|err| Error::plugin_failed(self.name(), err))?;
 
-        app.register_main_thread_module(Self { event_loop, window });
+        // Already added by the WindowPlugin, but the tick relies on it being there
+        app.add_event::<Event>();
+        app.register_main_thread_module(WinitWindow { event_loop, window })
     }
 }
 
@@ -150,24 +161,25 @@ impl MainThreadModule for WinitWindow {
         self.event_loop
             .run_on_demand(|event, elwt| {
                 elwt.exit();
-                if let winit::event::Event::WindowEvent {
-                    event: winit::event::WindowEvent::KeyboardInput { event, .. },
-                    ..
-                } = event
-                {
-                    let Some(key) = winit_to_key(event.physical_key) else {
-                        return;
-                    };
-
-                    match event.state {
-                        winit::event::ElementState::Pressed => {
-                            window.submit(Event::KeyPress(key));
-                        }
-                        winit::event::ElementState::Released => {
-                            window.submit(Event::KeyRelease(key));
+                let winit::event::Event::WindowEvent { event, .. } = event else {
+                    return;
+                };
+                let event = match event {
+                    winit::event::WindowEvent::KeyboardInput { event, .. } => {
+                        let Some(key) = winit_to_key(event.physical_key) else {
+                            return;
+                        };
+                        match event.state {
+                            winit::event::ElementState::Pressed => Event::KeyPress(key),
+                            winit::event::ElementState::Released => Event::KeyRelease(key),
                         }
                     }
-                }
+                    winit::event::WindowEvent::Focused(focused) => Event::Focus(focused),
+                    _ => return,
+                };
+                window.submit(event);
+                // Can only fail if the event type was never added, which build makes sure of
+                let _ = app.send(event);
             })
             .unwrap();
     }